@@ -0,0 +1,261 @@
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// A named file destined for a ZIP archive. Names may contain `/` to place
+/// the file in a folder.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+impl ArchiveEntry {
+    pub fn new(name: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            name: name.into(),
+            bytes: bytes.into(),
+        }
+    }
+}
+
+/// Build an in-memory ZIP archive from a list of entries.
+pub fn build_zip(entries: &[ArchiveEntry]) -> Result<Vec<u8>, String> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for entry in entries {
+        writer
+            .start_file(entry.name.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to archive: {}", entry.name, e))?;
+        writer
+            .write_all(&entry.bytes)
+            .map_err(|e| format!("Failed to write {} to archive: {}", entry.name, e))?;
+    }
+
+    let cursor = writer
+        .finish()
+        .map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok(cursor.into_inner())
+}
+
+/// Write entries as files under `dir`, creating folders as needed.
+pub async fn write_entries(entries: &[ArchiveEntry], dir: &str) -> Result<(), String> {
+    let dir = Path::new(dir);
+    for entry in entries {
+        let path = dir.join(&entry.name);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        tokio::fs::write(&path, &entry.bytes)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", entry.name, e))?;
+    }
+    Ok(())
+}
+
+/// MS-DOS date for 1980-01-01, the earliest a ZIP timestamp can express.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// Most entries a [`ZipStream`] holds. 0xFFFF in the end record would tell
+/// readers to look for ZIP64 records, which are never written.
+pub(crate) const MAX_STREAM_ENTRIES: u16 = 0xFFFE;
+
+/// Largest archive a [`ZipStream`] writes, for the same reason.
+const MAX_STREAM_SIZE: u64 = 0xFFFF_FFFE;
+
+/// Incremental ZIP encoder whose output can be sent as it is produced.
+///
+/// Each [`ZipStream::entry`] call returns the bytes for one file, and
+/// [`ZipStream::finish`] returns the closing central directory. Unlike
+/// [`build_zip`], the whole archive never has to be held in memory.
+///
+/// The `zip` crate's writer needs to seek back over written data, so this
+/// writes plain ZIP32: an entry that would take the archive past 4 GiB or
+/// [`MAX_STREAM_ENTRIES`] files is refused with an error instead.
+#[derive(Debug, Default)]
+pub struct ZipStream {
+    offset: u64,
+    central: Vec<u8>,
+    count: u16,
+}
+
+impl ZipStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode one file (local header plus data).
+    pub fn entry(&mut self, entry: &ArchiveEntry) -> Result<Vec<u8>, String> {
+        let name = entry.name.as_bytes();
+        let crc = crc32fast::hash(&entry.bytes);
+
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(&entry.bytes)
+            .map_err(|e| format!("Failed to compress {}: {}", entry.name, e))?;
+        let deflated = encoder
+            .finish()
+            .map_err(|e| format!("Failed to compress {}: {}", entry.name, e))?;
+
+        // Already-compressed formats (PNG, WOFF2, …) are stored as-is.
+        let (method, data) = if deflated.len() < entry.bytes.len() {
+            (8u16, deflated.as_slice())
+        } else {
+            (0u16, entry.bytes.as_slice())
+        };
+
+        // Everything written so far, plus this entry and the central
+        // directory, must stay addressable with 32-bit offsets.
+        let local_len = 30 + name.len() as u64 + data.len() as u64;
+        let central_len = self.central.len() as u64 + 46 + name.len() as u64;
+        let total = self.offset + local_len + central_len + 22;
+        let (Ok(size), Ok(compressed), Ok(offset), Ok(name_len)) = (
+            u32::try_from(entry.bytes.len()),
+            u32::try_from(data.len()),
+            u32::try_from(self.offset),
+            u16::try_from(name.len()),
+        ) else {
+            return Err(format!("Archive too large to add {}", entry.name));
+        };
+        if self.count >= MAX_STREAM_ENTRIES || total > MAX_STREAM_SIZE {
+            return Err(format!("Archive too large to add {}", entry.name));
+        }
+
+        // Fields shared by the local and central headers: version 2.0,
+        // UTF-8 names, method, DOS time/date, CRC, sizes and name length.
+        let mut common = Vec::with_capacity(26);
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0x0800u16.to_le_bytes());
+        common.extend_from_slice(&method.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&DOS_DATE.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&compressed.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&name_len.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        let mut local = Vec::with_capacity(30 + name.len() + data.len());
+        local.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        local.extend_from_slice(&common);
+        local.extend_from_slice(name);
+        local.extend_from_slice(data);
+
+        self.central
+            .extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes());
+        self.central.extend_from_slice(&common);
+        // Comment length, disk number, internal and external attributes.
+        self.central.extend_from_slice(&[0; 10]);
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name);
+
+        self.offset += local.len() as u64;
+        self.count += 1;
+        Ok(local)
+    }
+
+    /// Encode the central directory that ends the archive.
+    pub fn finish(self) -> Result<Vec<u8>, String> {
+        let (Ok(central_size), Ok(central_offset)) = (
+            u32::try_from(self.central.len()),
+            u32::try_from(self.offset),
+        ) else {
+            return Err("Archive too large".to_string());
+        };
+
+        let mut out = self.central;
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&self.count.to_le_bytes());
+        out.extend_from_slice(&self.count.to_le_bytes());
+        out.extend_from_slice(&central_size.to_le_bytes());
+        out.extend_from_slice(&central_offset.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    fn read_back(archive: Vec<u8>) -> Vec<(String, Vec<u8>)> {
+        let mut zip = ZipArchive::new(Cursor::new(archive)).unwrap();
+        (0..zip.len())
+            .map(|i| {
+                let mut file = zip.by_index(i).unwrap();
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes).unwrap();
+                (file.name().to_string(), bytes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stream_round_trips_through_the_zip_crate() {
+        let entries = [
+            ArchiveEntry::new("manifest.json", b"{\"files\": []}".repeat(50)),
+            // Incompressible bytes are stored rather than deflated.
+            ArchiveEntry::new(
+                "icons/favicon.png",
+                (0..=255u8).map(|b| b.wrapping_mul(151)).collect::<Vec<_>>(),
+            ),
+            ArchiveEntry::new("fonts/Ünïcode Sans/regular.woff2", Vec::new()),
+        ];
+
+        let mut zip = ZipStream::new();
+        let mut archive = Vec::new();
+        for entry in &entries {
+            archive.extend(zip.entry(entry).unwrap());
+        }
+        archive.extend(zip.finish().unwrap());
+
+        let files = read_back(archive);
+        assert_eq!(files.len(), entries.len());
+        for (entry, (name, bytes)) in entries.iter().zip(files) {
+            assert_eq!(entry.name, name);
+            assert_eq!(entry.bytes, bytes);
+        }
+    }
+
+    #[test]
+    fn empty_stream_is_a_valid_archive() {
+        assert!(read_back(ZipStream::new().finish().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn stream_refuses_entries_past_zip32_limits() {
+        let entry = ArchiveEntry::new("a.txt", b"hello".to_vec());
+
+        let mut zip = ZipStream::new();
+        zip.count = MAX_STREAM_ENTRIES;
+        assert!(zip.entry(&entry).is_err());
+
+        let mut zip = ZipStream::new();
+        zip.offset = MAX_STREAM_SIZE - 60;
+        assert!(zip.entry(&entry).is_err());
+        // A refused entry leaves the stream unchanged.
+        assert_eq!((zip.count, zip.central.len()), (0, 0));
+    }
+
+    #[test]
+    fn build_zip_round_trips() {
+        let entries = [
+            ArchiveEntry::new("favicon.ico", vec![0, 0, 1, 0]),
+            ArchiveEntry::new("site.webmanifest", b"{}".to_vec()),
+        ];
+        let files = read_back(build_zip(&entries).unwrap());
+        assert_eq!(files[1], ("site.webmanifest".to_string(), b"{}".to_vec()));
+    }
+}
@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::config::Config;
+use crate::credentials;
+use crate::diagnostics::{Diagnostic, Trace};
+use crate::error::ScanError;
+use crate::scanner::{FaviconInfo, ScanWarning};
+
+/// Windows tile metadata from `msapplication-*` meta tags and browserconfig.xml.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TileInfo {
+    /// Resolved browserconfig.xml URL, if one was found.
+    pub config_url: Option<String>,
+    pub tile_color: Option<String>,
+}
+
+/// Tile logo names and the pixel size each one implies.
+const TILE_LOGOS: &[(&str, &str)] = &[
+    ("square70x70logo", "70x70"),
+    ("square150x150logo", "150x150"),
+    ("wide310x150logo", "310x150"),
+    ("square310x310logo", "310x310"),
+    ("tileimage", "144x144"),
+];
+
+/// Map a tile logo name (browserconfig element or meta suffix) to its size.
+pub(crate) fn tile_logo_size(name: &str) -> Option<&'static str> {
+    let lower = name.to_lowercase();
+    TILE_LOGOS
+        .iter()
+        .find(|(logo, _)| *logo == lower)
+        .map(|(_, size)| *size)
+}
+
+/// Parse browserconfig.xml, returning the tile color and tile logos.
+pub fn parse_browserconfig(
+    xml: &str,
+    config_url: &Url,
+) -> Result<(Option<String>, Vec<FaviconInfo>), String> {
+    let doc =
+        roxmltree::Document::parse(xml).map_err(|e| format!("Invalid browserconfig XML: {}", e))?;
+
+    if !doc
+        .root_element()
+        .tag_name()
+        .name()
+        .eq_ignore_ascii_case("browserconfig")
+    {
+        return Err("Invalid browserconfig XML: missing <browserconfig> root".to_string());
+    }
+
+    let tile = match doc
+        .descendants()
+        .find(|n| n.tag_name().name().eq_ignore_ascii_case("tile"))
+    {
+        Some(t) => t,
+        None => return Ok((None, Vec::new())),
+    };
+
+    let mut tile_color = None;
+    let mut logos: Vec<FaviconInfo> = Vec::new();
+
+    for node in tile.children().filter(|n| n.is_element()) {
+        let name = node.tag_name().name();
+
+        if name.eq_ignore_ascii_case("tilecolor") {
+            tile_color = node
+                .text()
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty());
+            continue;
+        }
+
+        let size = match tile_logo_size(name) {
+            Some(s) => s,
+            None => continue,
+        };
+        let src = match node.attribute("src").map(str::trim) {
+            Some(s) if !s.is_empty() => s,
+            _ => continue,
+        };
+        let url = match config_url.join(src) {
+            Ok(u) => u.to_string(),
+            Err(_) => continue,
+        };
+        if logos.iter().any(|l| l.url == url) {
+            continue;
+        }
+
+        logos.push(FaviconInfo {
+            url,
+            rel: name.to_string(),
+            sizes: Some(size.to_string()),
+            mime_type: None,
+            media: None,
+            color: None,
+            purpose: None,
+            source: "browserconfig".to_string(),
+            details: None,
+        });
+    }
+
+    Ok((tile_color, logos))
+}
+
+/// Fetch and parse browserconfig.xml. Returns `None` on any failure; the
+/// reason is recorded in `diagnostics`.
+pub(crate) async fn fetch_browserconfig(
+    cfg: &Config,
+    config_url: &str,
+    client: &reqwest::Client,
+    warnings: &mut Vec<ScanWarning>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<(Option<String>, Vec<FaviconInfo>)> {
+    let mut trace = Trace::new("browserconfig", config_url);
+    let result = load_browserconfig(cfg, config_url, client, warnings, &mut trace).await;
+    diagnostics.push(trace.finish());
+    result
+}
+
+async fn load_browserconfig(
+    cfg: &Config,
+    config_url: &str,
+    client: &reqwest::Client,
+    warnings: &mut Vec<ScanWarning>,
+    trace: &mut Trace,
+) -> Option<(Option<String>, Vec<FaviconInfo>)> {
+    if !trace.allowed(cfg).await {
+        return None;
+    }
+    let resp = trace
+        .send(
+            cfg,
+            credentials::get(cfg, client, config_url).timeout(cfg.timeout_probe),
+        )
+        .await?;
+
+    let final_url = resp.url().clone();
+    let limit = cfg.max_html_size;
+    let body = trace.read(resp, limit).await?;
+    if body.truncated {
+        warnings.push(ScanWarning::too_large("browserconfig", config_url, limit));
+        trace.fail(ScanError::TooLarge { limit });
+        return None;
+    }
+    trace.parsed(body.bytes.len());
+    let text = String::from_utf8_lossy(&body.bytes);
+
+    parse_browserconfig(&text, &final_url)
+        .map_err(|e| trace.fail(ScanError::Parse(e)))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_url() -> Url {
+        Url::parse("https://example.com/ms/browserconfig.xml").unwrap()
+    }
+
+    #[test]
+    fn tile_logos_and_color_are_read() {
+        let xml = r##"<?xml version="1.0" encoding="utf-8"?>
+            <browserconfig>
+              <msapplication>
+                <tile>
+                  <square70x70logo src="small.png"/>
+                  <square150x150logo src="/medium.png"/>
+                  <Square310x310Logo src="https://cdn.example.net/large.png"/>
+                  <wide310x150logo src=" wide.png "/>
+                  <TileColor> #2b5797 </TileColor>
+                </tile>
+              </msapplication>
+            </browserconfig>"##;
+        let (color, logos) = parse_browserconfig(xml, &config_url()).unwrap();
+        assert_eq!(color.as_deref(), Some("#2b5797"));
+
+        let found: Vec<(&str, &str, Option<&str>)> = logos
+            .iter()
+            .map(|l| (l.url.as_str(), l.rel.as_str(), l.sizes.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "https://example.com/ms/small.png",
+                    "square70x70logo",
+                    Some("70x70")
+                ),
+                (
+                    "https://example.com/medium.png",
+                    "square150x150logo",
+                    Some("150x150")
+                ),
+                (
+                    "https://cdn.example.net/large.png",
+                    "Square310x310Logo",
+                    Some("310x310")
+                ),
+                (
+                    "https://example.com/ms/wide.png",
+                    "wide310x150logo",
+                    Some("310x150")
+                ),
+            ]
+        );
+        assert!(logos.iter().all(|l| l.source == "browserconfig"));
+    }
+
+    #[test]
+    fn unknown_and_empty_elements_are_skipped() {
+        let xml = r#"<browserconfig><msapplication><tile>
+                <square70x70logo src=""/>
+                <square150x150logo/>
+                <badge src="badge.png"/>
+                <TileColor></TileColor>
+            </tile></msapplication></browserconfig>"#;
+        let (color, logos) = parse_browserconfig(xml, &config_url()).unwrap();
+        assert_eq!(color, None);
+        assert!(logos.is_empty());
+
+        let (color, logos) = parse_browserconfig("<browserconfig/>", &config_url()).unwrap();
+        assert_eq!(color, None);
+        assert!(logos.is_empty());
+    }
+
+    #[test]
+    fn other_documents_are_rejected() {
+        assert!(parse_browserconfig("<html><body/></html>", &config_url()).is_err());
+        assert!(parse_browserconfig("<browserconfig>", &config_url()).is_err());
+    }
+
+    #[test]
+    fn tile_logo_sizes_ignore_case() {
+        assert_eq!(tile_logo_size("Wide310x150Logo"), Some("310x150"));
+        assert_eq!(tile_logo_size("TileImage"), Some("144x144"));
+        assert_eq!(tile_logo_size("TileColor"), None);
+    }
+}
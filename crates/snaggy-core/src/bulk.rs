@@ -0,0 +1,381 @@
+use std::collections::HashSet;
+
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::archive::{ArchiveEntry, ZipStream};
+use crate::config::Config;
+use crate::download::ByteStream;
+use crate::error::ScanError;
+use crate::scanner::Scanner;
+use crate::svg::RenderOptions;
+
+/// Archive chunks, in order. Concatenated, they form a complete ZIP file.
+pub type ZipChunks = ByteStream;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    Icon,
+    Font,
+    Image,
+    Logo,
+}
+
+/// One asset to include in a bulk archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkAsset {
+    pub url: String,
+    /// Decides the archive folder; guessed from the URL when omitted.
+    pub kind: Option<AssetKind>,
+    /// Font family, used for the `fonts/<family>/` folder.
+    pub family: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkRequest {
+    #[serde(default)]
+    pub assets: Vec<BulkAsset>,
+    /// Website to scan; its assets are added to `assets`, filtered by `include`.
+    pub scan_url: Option<String>,
+    /// Kinds of scanned assets to include; all kinds when empty.
+    #[serde(default)]
+    pub include: Vec<AssetKind>,
+}
+
+/// Per-file outcome, written to `manifest.json` inside the archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkManifestEntry {
+    pub url: String,
+    pub kind: Option<AssetKind>,
+    /// Path inside the archive; `None` if the download failed.
+    pub path: Option<String>,
+    pub content_type: Option<String>,
+    pub size: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkManifest {
+    /// Scanned website, if the request named one.
+    pub source: Option<String>,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub files: Vec<BulkManifestEntry>,
+}
+
+const FONT_EXTENSIONS: &[&str] = &["woff2", "woff", "ttf", "otf", "eot"];
+
+/// Keep a path segment to `[A-Za-z0-9._-]` so names can't escape their folder.
+fn sanitize_segment(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    cleaned.trim_matches(['.', '-']).to_string()
+}
+
+fn url_file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let last = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    let name = sanitize_segment(last);
+    if name.is_empty() || url.starts_with("data:") {
+        "asset".to_string()
+    } else {
+        name
+    }
+}
+
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    Some(match mime {
+        "image/png" => "png",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "image/x-win-bitmap" => "cur",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "font/woff2" => "woff2",
+        "font/woff" | "application/font-woff" => "woff",
+        "font/ttf" | "font/sfnt" | "application/x-font-ttf" => "ttf",
+        "font/otf" => "otf",
+        _ => return None,
+    })
+}
+
+fn folder_for(asset: &BulkAsset) -> String {
+    let kind = asset.kind.or_else(|| {
+        let name = url_file_name(&asset.url).to_lowercase();
+        let ext = name.rsplit_once('.').map(|(_, e)| e).unwrap_or_default();
+        FONT_EXTENSIONS.contains(&ext).then_some(AssetKind::Font)
+    });
+    match kind {
+        Some(AssetKind::Icon) => "icons".to_string(),
+        Some(AssetKind::Image) => "images".to_string(),
+        Some(AssetKind::Logo) => "logos".to_string(),
+        Some(AssetKind::Font) => match asset.family.as_deref().map(sanitize_segment) {
+            Some(family) if !family.is_empty() => format!("fonts/{}", family),
+            _ => "fonts".to_string(),
+        },
+        None => "other".to_string(),
+    }
+}
+
+/// Per-kind size limit for one asset.
+fn size_limit(cfg: &Config, asset: &BulkAsset) -> u64 {
+    match asset.kind {
+        Some(AssetKind::Font) => cfg.max_font_size,
+        Some(AssetKind::Icon | AssetKind::Image | AssetKind::Logo) => cfg.max_image_size,
+        None => cfg.max_download_size,
+    }
+}
+
+/// Pick a unique archive path for a downloaded asset.
+fn archive_path(asset: &BulkAsset, content_type: &str, taken: &mut HashSet<String>) -> String {
+    let folder = folder_for(asset);
+    let mut name = url_file_name(&asset.url);
+    if !name.contains('.')
+        && let Some(ext) = extension_for(content_type)
+    {
+        name = format!("{}.{}", name, ext);
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((s, e)) => (s.to_string(), format!(".{}", e)),
+        None => (name.clone(), String::new()),
+    };
+
+    let mut path = format!("{}/{}", folder, name);
+    let mut n = 2;
+    while !taken.insert(path.clone()) {
+        path = format!("{}/{}-{}{}", folder, stem, n, ext);
+        n += 1;
+    }
+    path
+}
+
+/// Resolve a request into the final, de-duplicated asset list.
+pub async fn plan_bulk_assets(
+    scanner: &Scanner,
+    request: &BulkRequest,
+) -> Result<Vec<BulkAsset>, ScanError> {
+    let mut assets = request.assets.clone();
+
+    if let Some(scan_url) = &request.scan_url {
+        let result = scanner.scan_website(scan_url).await?;
+        let wanted = |kind| request.include.is_empty() || request.include.contains(&kind);
+        let asset = |url: &str, kind, family: Option<&str>| BulkAsset {
+            url: url.to_string(),
+            kind: Some(kind),
+            family: family.map(|f| f.to_string()),
+        };
+
+        if wanted(AssetKind::Icon) {
+            assets.extend(
+                result
+                    .favicons
+                    .iter()
+                    .map(|f| asset(&f.url, AssetKind::Icon, None)),
+            );
+        }
+        if wanted(AssetKind::Font) {
+            for font in &result.fonts {
+                assets.extend(
+                    font.variants
+                        .iter()
+                        .map(|v| asset(&v.url, AssetKind::Font, Some(&font.family))),
+                );
+            }
+        }
+        if wanted(AssetKind::Image) {
+            assets.extend(
+                result
+                    .images
+                    .iter()
+                    .map(|i| asset(&i.url, AssetKind::Image, None)),
+            );
+        }
+        if wanted(AssetKind::Logo) {
+            assets.extend(
+                result
+                    .logos
+                    .iter()
+                    .filter_map(|l| l.url.as_deref())
+                    .map(|url| asset(url, AssetKind::Logo, None)),
+            );
+        }
+    }
+
+    let mut seen = HashSet::new();
+    assets.retain(|a| !a.url.trim().is_empty() && seen.insert(a.url.clone()));
+
+    if assets.is_empty() {
+        return Err(ScanError::InvalidRequest(
+            "No assets to download".to_string(),
+        ));
+    }
+    let max = scanner.config().max_bulk_assets;
+    if assets.len() > max {
+        return Err(ScanError::InvalidRequest(format!(
+            "Too many assets: {} (max {})",
+            assets.len(),
+            max
+        )));
+    }
+    Ok(assets)
+}
+
+/// A downloaded asset's bytes and content type, or the download error.
+type Download = Result<(Vec<u8>, String), ScanError>;
+
+struct BulkState {
+    downloads: BoxStream<'static, (BulkAsset, Download)>,
+    zip: ZipStream,
+    taken: HashSet<String>,
+    manifest: BulkManifest,
+}
+
+impl BulkState {
+    fn add(&mut self, asset: BulkAsset, result: Download) -> Result<Vec<u8>, ScanError> {
+        let (bytes, content_type) = match result {
+            Ok(r) => r,
+            Err(e) => {
+                self.manifest.failed += 1;
+                self.manifest.files.push(BulkManifestEntry {
+                    url: asset.url,
+                    kind: asset.kind,
+                    path: None,
+                    content_type: None,
+                    size: None,
+                    error: Some(e.to_string()),
+                });
+                return Ok(Vec::new());
+            }
+        };
+
+        let path = archive_path(&asset, &content_type, &mut self.taken);
+        let chunk = self
+            .zip
+            .entry(&ArchiveEntry::new(path.clone(), bytes.as_slice()))
+            .map_err(ScanError::Internal)?;
+        self.manifest.succeeded += 1;
+        self.manifest.files.push(BulkManifestEntry {
+            url: asset.url,
+            kind: asset.kind,
+            path: Some(path),
+            content_type: Some(content_type),
+            size: Some(bytes.len() as u64),
+            error: None,
+        });
+        Ok(chunk)
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, ScanError> {
+        let json = serde_json::to_vec_pretty(&self.manifest)
+            .map_err(|e| ScanError::Internal(format!("Failed to serialize manifest: {}", e)))?;
+        let mut chunk = self
+            .zip
+            .entry(&ArchiveEntry::new("manifest.json", json))
+            .map_err(ScanError::Internal)?;
+        chunk.extend(self.zip.finish().map_err(ScanError::Internal)?);
+        Ok(chunk)
+    }
+}
+
+/// Download assets with bounded concurrency and stream them as a ZIP.
+///
+/// Files are grouped into `icons/`, `fonts/<family>/`, `images/`, `logos/`
+/// and `other/`. A `manifest.json` at the root lists every asset, including
+/// the error for each one that failed.
+pub fn bulk_zip_stream(
+    scanner: Scanner,
+    assets: Vec<BulkAsset>,
+    source: Option<String>,
+) -> ZipChunks {
+    let concurrency = scanner.config().download_concurrency;
+    let downloads = stream::iter(assets)
+        .map(move |asset| {
+            let scanner = scanner.clone();
+            async move {
+                let max_size = size_limit(scanner.config(), &asset);
+                let result = scanner
+                    .fetch_asset_bytes(&asset.url, &RenderOptions::default(), max_size)
+                    .await;
+                (asset, result)
+            }
+        })
+        .buffered(concurrency)
+        .boxed();
+
+    let state = BulkState {
+        downloads,
+        zip: ZipStream::new(),
+        taken: HashSet::new(),
+        manifest: BulkManifest {
+            source,
+            ..BulkManifest::default()
+        },
+    };
+
+    stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        match state.downloads.next().await {
+            Some((asset, result)) => {
+                let chunk = state.add(asset, result);
+                // Stop after an archive error; the output would be corrupt.
+                let next = chunk.is_ok().then_some(state);
+                Some((chunk, next))
+            }
+            None => Some((state.finish(), None)),
+        }
+    })
+    .filter(|chunk| futures::future::ready(!matches!(chunk, Ok(c) if c.is_empty())))
+    .boxed()
+}
+
+/// Plan a bulk request and return the archive as a stream of chunks.
+pub async fn bulk_download(
+    scanner: &Scanner,
+    request: &BulkRequest,
+) -> Result<ZipChunks, ScanError> {
+    let assets = plan_bulk_assets(scanner, request).await?;
+    Ok(bulk_zip_stream(
+        scanner.clone(),
+        assets,
+        request.scan_url.clone(),
+    ))
+}
+
+/// Plan a bulk request and write the archive to a file as it is produced.
+pub async fn save_bulk_download(
+    scanner: &Scanner,
+    request: &BulkRequest,
+    save_path: &str,
+) -> Result<(), ScanError> {
+    let mut chunks = bulk_download(scanner, request).await?;
+    let mut file = tokio::fs::File::create(save_path)
+        .await
+        .map_err(|e| ScanError::Io(format!("Failed to create file: {}", e)))?;
+
+    while let Some(chunk) = chunks.next().await {
+        file.write_all(&chunk?)
+            .await
+            .map_err(|e| ScanError::Io(format!("Failed to write file: {}", e)))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| ScanError::Io(format!("Failed to write file: {}", e)))?;
+    Ok(())
+}
@@ -1,8 +1,19 @@
-use std::sync::OnceLock;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
 use std::time::Duration;
 
+use serde::Deserialize;
+use url::Url;
+
+use crate::archive::MAX_STREAM_ENTRIES;
+use crate::credentials::SiteCredentials;
+
 /// Central configuration for Snaggy scanner.
-/// All timeouts are configurable via environment variables, with sensible defaults.
+/// Every setting can come from a config file or an environment variable, with sensible defaults.
+/// A [`Scanner`](crate::scanner::Scanner) owns one; [`Config::load`] layers
+/// defaults < config file < environment.
 #[derive(Debug, Clone)]
 pub struct Config {
     /// Global HTTP client timeout (default: 30s)
@@ -29,11 +40,125 @@ pub struct Config {
     /// Env: SNAGGY_MAX_IMPORTS
     pub max_imports: usize,
 
+    /// Maximum HTML page size in bytes; larger pages are truncated (default: 5 MiB).
+    /// Also caps web app manifests and browserconfig.xml.
+    /// Env: SNAGGY_MAX_HTML_SIZE
+    pub max_html_size: u64,
+
+    /// Maximum stylesheet size in bytes; larger stylesheets are truncated (default: 2 MiB)
+    /// Env: SNAGGY_MAX_CSS_SIZE
+    pub max_css_size: u64,
+
+    /// Maximum icon/image size in bytes for inspection, previews and image downloads (default: 10 MiB)
+    /// Env: SNAGGY_MAX_IMAGE_SIZE
+    pub max_image_size: u64,
+
+    /// Maximum font file size in bytes for kits and bulk downloads (default: 20 MiB)
+    /// Env: SNAGGY_MAX_FONT_SIZE
+    pub max_font_size: u64,
+
+    /// Maximum size of any other downloaded asset, in bytes (default: 50 MiB)
+    /// Env: SNAGGY_MAX_DOWNLOAD_SIZE
+    pub max_download_size: u64,
+
+    /// Maximum number of parallel asset downloads for kits and bulk archives (default: 6)
+    /// Env: SNAGGY_DOWNLOAD_CONCURRENCY
+    pub download_concurrency: usize,
+
+    /// Maximum number of assets in one bulk archive (default: 500)
+    /// Env: SNAGGY_MAX_BULK_ASSETS
+    pub max_bulk_assets: usize,
+
     /// User-Agent string
+    /// Env: SNAGGY_USER_AGENT
     pub user_agent: String,
 
     /// Maximum redirect hops (default: 10)
+    /// Env: SNAGGY_MAX_REDIRECTS
     pub max_redirects: usize,
+
+    /// Retries for timeouts, dropped connections and 408/429/502/503/504
+    /// responses on GET and HEAD requests, at most 10 (default: 2)
+    /// Env: SNAGGY_MAX_RETRIES
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled (with jitter) for each further one (default: 250ms)
+    /// Env: SNAGGY_RETRY_BASE_DELAY_MS
+    pub retry_base_delay: Duration,
+
+    /// Longest wait between retries; a longer `Retry-After` ends retrying (default: 10s)
+    /// Env: SNAGGY_RETRY_MAX_DELAY_MS
+    pub retry_max_delay: Duration,
+
+    /// Idle connections kept open per host for reuse (default: 8)
+    /// Env: SNAGGY_POOL_MAX_IDLE_PER_HOST
+    pub pool_max_idle_per_host: usize,
+
+    /// How long an idle pooled connection is kept open (default: 90s)
+    /// Env: SNAGGY_POOL_IDLE_TIMEOUT
+    pub pool_idle_timeout: Duration,
+
+    /// Negotiate HTTP/2 with servers that support it (default: true)
+    /// Env: SNAGGY_HTTP2
+    pub http2: bool,
+
+    /// Proxy for all requests: an `http://`, `https://`, `socks5://` or `socks5h://` URL.
+    /// When no proxy is configured, the standard `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY` variables apply.
+    /// Env: SNAGGY_PROXY
+    pub proxy: Option<String>,
+
+    /// Proxy for `http://` URLs, taking precedence over `proxy`
+    /// Env: SNAGGY_HTTP_PROXY
+    pub http_proxy: Option<String>,
+
+    /// Proxy for `https://` URLs, taking precedence over `proxy`
+    /// Env: SNAGGY_HTTPS_PROXY
+    pub https_proxy: Option<String>,
+
+    /// Hosts, domains (`.example.com`) and IP ranges reached without the proxy (default: none)
+    /// Env: SNAGGY_NO_PROXY (comma-separated)
+    pub no_proxy: Vec<String>,
+
+    /// Proxy credentials, unless given in the proxy URL
+    /// Env: SNAGGY_PROXY_USERNAME, SNAGGY_PROXY_PASSWORD
+    pub proxy_username: Option<String>,
+    pub proxy_password: Option<String>,
+
+    /// PEM files with extra trusted root certificates (default: none)
+    /// Env: SNAGGY_CA_CERTIFICATES (comma-separated paths)
+    pub ca_certificates: Vec<String>,
+
+    /// Also trust the operating system's certificate store (default: false)
+    /// Env: SNAGGY_TLS_NATIVE_ROOTS
+    pub tls_native_roots: bool,
+
+    /// Accept any certificate, including self-signed and expired ones (default: false).
+    /// Only for debugging; it disables protection against interception.
+    /// Env: SNAGGY_TLS_INSECURE
+    pub tls_insecure: bool,
+
+    /// Headers, cookies and credentials for specific hosts (default: none). Config file only.
+    pub credentials: Vec<SiteCredentials>,
+
+    /// Site-relative icon paths probed on every scan (default: favicon.ico and apple-touch-icon variants)
+    /// Env: SNAGGY_WELL_KNOWN_ICONS (comma-separated)
+    pub well_known_icons: Vec<String>,
+
+    /// Allow requests to loopback, private, link-local and other reserved addresses (default: false)
+    /// Env: SNAGGY_ALLOW_PRIVATE_NETWORKS
+    pub allow_private_networks: bool,
+
+    /// Hosts exempt from the private-address check; `*.example.com` matches subdomains (default: none)
+    /// Env: SNAGGY_ALLOWED_HOSTS (comma-separated)
+    pub allowed_hosts: Vec<String>,
+
+    /// Hosts that are never fetched; `*.example.com` matches subdomains (default: none)
+    /// Env: SNAGGY_DENIED_HOSTS (comma-separated)
+    pub denied_hosts: Vec<String>,
+
+    /// URL schemes that may be fetched (default: http, https)
+    /// Env: SNAGGY_ALLOWED_SCHEMES (comma-separated)
+    pub allowed_schemes: Vec<String>,
 }
 
 impl Default for Config {
@@ -45,51 +170,496 @@ impl Default for Config {
             timeout_image: Duration::from_secs(10),
             max_stylesheets: 20,
             max_imports: 5,
+            max_html_size: 5 * 1024 * 1024,
+            max_css_size: 2 * 1024 * 1024,
+            max_image_size: 10 * 1024 * 1024,
+            max_font_size: 20 * 1024 * 1024,
+            max_download_size: 50 * 1024 * 1024,
+            download_concurrency: 6,
+            max_bulk_assets: 500,
             user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string(),
             max_redirects: 10,
+            max_retries: 2,
+            retry_base_delay: Duration::from_millis(250),
+            retry_max_delay: Duration::from_secs(10),
+            pool_max_idle_per_host: 8,
+            pool_idle_timeout: Duration::from_secs(90),
+            http2: true,
+            proxy: None,
+            http_proxy: None,
+            https_proxy: None,
+            no_proxy: Vec::new(),
+            proxy_username: None,
+            proxy_password: None,
+            ca_certificates: Vec::new(),
+            tls_native_roots: false,
+            tls_insecure: false,
+            credentials: Vec::new(),
+            well_known_icons: [
+                "/favicon.ico",
+                "/favicon.svg",
+                "/favicon.png",
+                "/apple-touch-icon.png",
+                "/apple-touch-icon-precomposed.png",
+                "/apple-touch-icon-180x180.png",
+                "/apple-touch-icon-152x152.png",
+                "/apple-touch-icon-120x120.png",
+            ]
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+            allow_private_networks: false,
+            allowed_hosts: Vec::new(),
+            denied_hosts: Vec::new(),
+            allowed_schemes: vec!["http".to_string(), "https".to_string()],
         }
     }
 }
 
 impl Config {
-    /// Load configuration from environment variables, falling back to defaults.
-    pub fn from_env() -> Self {
-        let defaults = Self::default();
+    /// Defaults overridden by `SNAGGY_*` environment variables.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_layers(&[ConfigLayer::from_env()?])
+    }
 
+    /// Defaults, then the config file at `path` (if any), then `SNAGGY_*`
+    /// environment variables.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut layers = Vec::new();
+        if let Some(path) = path {
+            layers.push(ConfigLayer::from_file(path)?);
+        }
+        layers.push(ConfigLayer::from_env()?);
+        Self::from_layers(&layers)
+    }
+
+    /// Apply `layers` in order on top of the defaults and validate the result.
+    pub fn from_layers(layers: &[ConfigLayer]) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        for layer in layers {
+            layer.apply(&mut config);
+        }
+        for (i, site) in config.credentials.iter_mut().enumerate() {
+            site.load_cookies()
+                .map_err(|e| ConfigError::new(format!("credentials[{}].cookies_file", i), e))?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let nonzero = [
+            ("timeout_global", self.timeout_global.as_secs()),
+            ("timeout_request", self.timeout_request.as_secs()),
+            ("timeout_probe", self.timeout_probe.as_secs()),
+            ("timeout_image", self.timeout_image.as_secs()),
+            ("pool_idle_timeout", self.pool_idle_timeout.as_secs()),
+            ("max_html_size", self.max_html_size),
+            ("max_css_size", self.max_css_size),
+            ("max_image_size", self.max_image_size),
+            ("max_font_size", self.max_font_size),
+            ("max_download_size", self.max_download_size),
+            ("download_concurrency", self.download_concurrency as u64),
+            ("max_bulk_assets", self.max_bulk_assets as u64),
+        ];
+        for (key, value) in nonzero {
+            if value == 0 {
+                return Err(ConfigError::new(key, "must be greater than 0"));
+            }
+        }
+        // One entry is kept for the archive's manifest.json.
+        if self.max_bulk_assets >= usize::from(MAX_STREAM_ENTRIES) {
+            return Err(ConfigError::new(
+                "max_bulk_assets",
+                format!("must be below {}", MAX_STREAM_ENTRIES),
+            ));
+        }
+        if self.max_retries > MAX_RETRIES {
+            return Err(ConfigError::new(
+                "max_retries",
+                format!("must be at most {}", MAX_RETRIES),
+            ));
+        }
+        if self.retry_base_delay > self.retry_max_delay {
+            return Err(ConfigError::new(
+                "retry_base_delay_ms",
+                "must not exceed retry_max_delay_ms",
+            ));
+        }
+        if self.user_agent.trim().is_empty() {
+            return Err(ConfigError::new("user_agent", "must not be empty"));
+        }
+        if self.allowed_schemes.is_empty() {
+            return Err(ConfigError::new(
+                "allowed_schemes",
+                "must list at least one scheme",
+            ));
+        }
+        for (key, proxy) in [
+            ("proxy", &self.proxy),
+            ("http_proxy", &self.http_proxy),
+            ("https_proxy", &self.https_proxy),
+        ] {
+            if let Some(proxy) = proxy {
+                validate_proxy_url(proxy).map_err(|message| ConfigError::new(key, message))?;
+            }
+        }
+        if self.proxy_username.is_some() != self.proxy_password.is_some() {
+            return Err(ConfigError::new(
+                "proxy_username",
+                "proxy_username and proxy_password must be set together",
+            ));
+        }
+        for (i, site) in self.credentials.iter().enumerate() {
+            site.validate()
+                .map_err(|e| ConfigError::new(format!("credentials[{}]", i), e))?;
+        }
+        if let Some(path) = self.well_known_icons.iter().find(|p| !p.starts_with('/')) {
+            return Err(ConfigError::new(
+                "well_known_icons",
+                format!("\"{}\" must start with \"/\"", path),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn validate_proxy_url(proxy: &str) -> Result<(), String> {
+    let url = Url::parse(proxy).map_err(|e| format!("\"{}\" is not a valid URL: {}", proxy, e))?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(format!(
+            "\"{}\" must use http, https, socks5 or socks5h",
+            proxy
+        ));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("\"{}\" has no host", proxy));
+    }
+    Ok(())
+}
+
+/// A configuration value that could not be read or is out of range.
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    /// The setting, environment variable or file at fault.
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
         Self {
-            timeout_global: read_duration_env("SNAGGY_TIMEOUT_GLOBAL", defaults.timeout_global),
-            timeout_request: read_duration_env("SNAGGY_TIMEOUT_REQUEST", defaults.timeout_request),
-            timeout_probe: read_duration_env("SNAGGY_TIMEOUT_PROBE", defaults.timeout_probe),
-            timeout_image: read_duration_env("SNAGGY_TIMEOUT_IMAGE", defaults.timeout_image),
-            max_stylesheets: read_usize_env("SNAGGY_MAX_STYLESHEETS", defaults.max_stylesheets),
-            max_imports: read_usize_env("SNAGGY_MAX_IMPORTS", defaults.max_imports),
-            user_agent: std::env::var("SNAGGY_USER_AGENT").unwrap_or(defaults.user_agent),
-            max_redirects: read_usize_env("SNAGGY_MAX_REDIRECTS", defaults.max_redirects),
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Environment variable naming the config file.
+pub const CONFIG_PATH_ENV: &str = "SNAGGY_CONFIG";
+
+/// Most retries allowed for one request.
+pub const MAX_RETRIES: u32 = 10;
+
+/// One source of settings: a config file or the environment.
+///
+/// Keys match the [`Config`] field names, with timeouts in seconds and
+/// retry delays in milliseconds (`_ms`); unset keys leave the lower layers
+/// alone. Server settings live under `[server]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub timeout_global: Option<u64>,
+    pub timeout_request: Option<u64>,
+    pub timeout_probe: Option<u64>,
+    pub timeout_image: Option<u64>,
+    pub max_stylesheets: Option<usize>,
+    pub max_imports: Option<usize>,
+    pub max_html_size: Option<u64>,
+    pub max_css_size: Option<u64>,
+    pub max_image_size: Option<u64>,
+    pub max_font_size: Option<u64>,
+    pub max_download_size: Option<u64>,
+    pub download_concurrency: Option<usize>,
+    pub max_bulk_assets: Option<usize>,
+    pub user_agent: Option<String>,
+    pub max_redirects: Option<usize>,
+    pub max_retries: Option<u32>,
+    pub retry_base_delay_ms: Option<u64>,
+    pub retry_max_delay_ms: Option<u64>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub pool_idle_timeout: Option<u64>,
+    pub http2: Option<bool>,
+    pub proxy: Option<String>,
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<Vec<String>>,
+    pub proxy_username: Option<String>,
+    pub proxy_password: Option<String>,
+    pub ca_certificates: Option<Vec<String>>,
+    pub tls_native_roots: Option<bool>,
+    pub tls_insecure: Option<bool>,
+    pub credentials: Option<Vec<SiteCredentials>>,
+    pub well_known_icons: Option<Vec<String>>,
+    pub allow_private_networks: Option<bool>,
+    pub allowed_hosts: Option<Vec<String>>,
+    pub denied_hosts: Option<Vec<String>>,
+    pub allowed_schemes: Option<Vec<String>>,
+    pub server: ServerLayer,
+}
+
+/// Settings for the web server binary.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerLayer {
+    /// Env: SNAGGY_PORT
+    pub port: Option<u16>,
+    /// Listen address. Env: SNAGGY_BIND
+    pub bind: Option<String>,
+    /// Allowed CORS origins; `*` allows any. Env: SNAGGY_CORS_ORIGINS (comma-separated)
+    pub cors_origins: Option<Vec<String>>,
+    /// Send the config file's credentials on every caller's scans.
+    /// Env: SNAGGY_SHARE_CREDENTIALS
+    pub share_credentials: Option<bool>,
+}
+
+impl ConfigLayer {
+    /// Read a TOML (`.toml`) or YAML (`.yaml`, `.yml`) config file.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let key = path.display().to_string();
+        let text =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::new(&key, e.to_string()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => {
+                toml::from_str(&text).map_err(|e| ConfigError::new(&key, e.to_string()))
+            }
+            Some("yaml" | "yml") => {
+                // An empty YAML document parses as null rather than an empty map.
+                if text.trim().is_empty() {
+                    return Ok(Self::default());
+                }
+                serde_yaml_ng::from_str(&text).map_err(|e| ConfigError::new(&key, e.to_string()))
+            }
+            _ => Err(ConfigError::new(
+                key,
+                "unsupported file type (expected .toml, .yaml or .yml)",
+            )),
+        }
+    }
+
+    /// Read the `SNAGGY_*` environment variables.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+            timeout_global: read_env("SNAGGY_TIMEOUT_GLOBAL")?,
+            timeout_request: read_env("SNAGGY_TIMEOUT_REQUEST")?,
+            timeout_probe: read_env("SNAGGY_TIMEOUT_PROBE")?,
+            timeout_image: read_env("SNAGGY_TIMEOUT_IMAGE")?,
+            max_stylesheets: read_env("SNAGGY_MAX_STYLESHEETS")?,
+            max_imports: read_env("SNAGGY_MAX_IMPORTS")?,
+            max_html_size: read_env("SNAGGY_MAX_HTML_SIZE")?,
+            max_css_size: read_env("SNAGGY_MAX_CSS_SIZE")?,
+            max_image_size: read_env("SNAGGY_MAX_IMAGE_SIZE")?,
+            max_font_size: read_env("SNAGGY_MAX_FONT_SIZE")?,
+            max_download_size: read_env("SNAGGY_MAX_DOWNLOAD_SIZE")?,
+            download_concurrency: read_env("SNAGGY_DOWNLOAD_CONCURRENCY")?,
+            max_bulk_assets: read_env("SNAGGY_MAX_BULK_ASSETS")?,
+            user_agent: std::env::var("SNAGGY_USER_AGENT").ok(),
+            max_redirects: read_env("SNAGGY_MAX_REDIRECTS")?,
+            max_retries: read_env("SNAGGY_MAX_RETRIES")?,
+            retry_base_delay_ms: read_env("SNAGGY_RETRY_BASE_DELAY_MS")?,
+            retry_max_delay_ms: read_env("SNAGGY_RETRY_MAX_DELAY_MS")?,
+            pool_max_idle_per_host: read_env("SNAGGY_POOL_MAX_IDLE_PER_HOST")?,
+            pool_idle_timeout: read_env("SNAGGY_POOL_IDLE_TIMEOUT")?,
+            http2: read_bool_env("SNAGGY_HTTP2")?,
+            proxy: std::env::var("SNAGGY_PROXY").ok(),
+            http_proxy: std::env::var("SNAGGY_HTTP_PROXY").ok(),
+            https_proxy: std::env::var("SNAGGY_HTTPS_PROXY").ok(),
+            no_proxy: read_list_env("SNAGGY_NO_PROXY"),
+            proxy_username: std::env::var("SNAGGY_PROXY_USERNAME").ok(),
+            proxy_password: std::env::var("SNAGGY_PROXY_PASSWORD").ok(),
+            ca_certificates: read_list_env("SNAGGY_CA_CERTIFICATES"),
+            tls_native_roots: read_bool_env("SNAGGY_TLS_NATIVE_ROOTS")?,
+            tls_insecure: read_bool_env("SNAGGY_TLS_INSECURE")?,
+            credentials: None,
+            well_known_icons: read_list_env("SNAGGY_WELL_KNOWN_ICONS"),
+            allow_private_networks: read_bool_env("SNAGGY_ALLOW_PRIVATE_NETWORKS")?,
+            allowed_hosts: read_list_env("SNAGGY_ALLOWED_HOSTS"),
+            denied_hosts: read_list_env("SNAGGY_DENIED_HOSTS"),
+            allowed_schemes: read_list_env("SNAGGY_ALLOWED_SCHEMES"),
+            server: ServerLayer {
+                port: read_env("SNAGGY_PORT")?,
+                bind: std::env::var("SNAGGY_BIND").ok(),
+                cors_origins: read_list_env("SNAGGY_CORS_ORIGINS"),
+                share_credentials: read_bool_env("SNAGGY_SHARE_CREDENTIALS")?,
+            },
+        })
+    }
+
+    /// Overwrite the fields of `config` that this layer sets.
+    pub fn apply(&self, config: &mut Config) {
+        fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *target = value.clone();
+            }
         }
+        // An empty string clears the setting, e.g. `SNAGGY_PROXY=` over a file's proxy.
+        fn set_optional(target: &mut Option<String>, value: &Option<String>) {
+            if let Some(value) = value {
+                *target = Some(value.clone()).filter(|v| !v.is_empty());
+            }
+        }
+        fn set_millis(target: &mut Duration, value: Option<u64>) {
+            if let Some(ms) = value {
+                *target = Duration::from_millis(ms);
+            }
+        }
+        fn set_secs(target: &mut Duration, value: Option<u64>) {
+            if let Some(secs) = value {
+                *target = Duration::from_secs(secs);
+            }
+        }
+
+        set_secs(&mut config.timeout_global, self.timeout_global);
+        set_secs(&mut config.timeout_request, self.timeout_request);
+        set_secs(&mut config.timeout_probe, self.timeout_probe);
+        set_secs(&mut config.timeout_image, self.timeout_image);
+        set(&mut config.max_stylesheets, &self.max_stylesheets);
+        set(&mut config.max_imports, &self.max_imports);
+        set(&mut config.max_html_size, &self.max_html_size);
+        set(&mut config.max_css_size, &self.max_css_size);
+        set(&mut config.max_image_size, &self.max_image_size);
+        set(&mut config.max_font_size, &self.max_font_size);
+        set(&mut config.max_download_size, &self.max_download_size);
+        set(&mut config.download_concurrency, &self.download_concurrency);
+        set(&mut config.max_bulk_assets, &self.max_bulk_assets);
+        set(&mut config.user_agent, &self.user_agent);
+        set(&mut config.max_redirects, &self.max_redirects);
+        set(&mut config.max_retries, &self.max_retries);
+        set_millis(&mut config.retry_base_delay, self.retry_base_delay_ms);
+        set_millis(&mut config.retry_max_delay, self.retry_max_delay_ms);
+        set(
+            &mut config.pool_max_idle_per_host,
+            &self.pool_max_idle_per_host,
+        );
+        set_secs(&mut config.pool_idle_timeout, self.pool_idle_timeout);
+        set(&mut config.http2, &self.http2);
+        set_optional(&mut config.proxy, &self.proxy);
+        set_optional(&mut config.http_proxy, &self.http_proxy);
+        set_optional(&mut config.https_proxy, &self.https_proxy);
+        set(&mut config.no_proxy, &self.no_proxy);
+        set_optional(&mut config.proxy_username, &self.proxy_username);
+        set_optional(&mut config.proxy_password, &self.proxy_password);
+        set(&mut config.ca_certificates, &self.ca_certificates);
+        set(&mut config.tls_native_roots, &self.tls_native_roots);
+        set(&mut config.tls_insecure, &self.tls_insecure);
+        set(&mut config.credentials, &self.credentials);
+        set(&mut config.well_known_icons, &self.well_known_icons);
+        set(
+            &mut config.allow_private_networks,
+            &self.allow_private_networks,
+        );
+        set(&mut config.allowed_hosts, &self.allowed_hosts);
+        set(&mut config.denied_hosts, &self.denied_hosts);
+        set(&mut config.allowed_schemes, &self.allowed_schemes);
     }
 }
 
-/// Read a duration (in seconds) from an environment variable.
-fn read_duration_env(key: &str, default: Duration) -> Duration {
-    std::env::var(key)
-        .ok()
-        .and_then(|v| v.parse::<u64>().ok())
-        .map(Duration::from_secs)
-        .unwrap_or(default)
+/// Parse an environment variable, if set.
+fn read_env<T>(key: &str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match std::env::var(key) {
+        Ok(v) => v
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| ConfigError::new(key, format!("invalid value \"{}\": {}", v, e))),
+        Err(_) => Ok(None),
+    }
 }
 
-/// Read a usize from an environment variable.
-fn read_usize_env(key: &str, default: usize) -> usize {
-    std::env::var(key)
-        .ok()
-        .and_then(|v| v.parse::<usize>().ok())
-        .unwrap_or(default)
+/// Read a boolean (`true`/`false`, `1`/`0`, `yes`/`no`) from an environment variable.
+fn read_bool_env(key: &str) -> Result<Option<bool>, ConfigError> {
+    match std::env::var(key) {
+        Ok(v) => match v.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(Some(true)),
+            "false" | "0" | "no" => Ok(Some(false)),
+            _ => Err(ConfigError::new(
+                key,
+                format!("invalid value \"{}\": expected true or false", v),
+            )),
+        },
+        Err(_) => Ok(None),
+    }
 }
 
-/// Global singleton config, initialized once on first access.
-static CONFIG: OnceLock<Config> = OnceLock::new();
+/// Read a comma-separated list from an environment variable.
+fn read_list_env(key: &str) -> Option<Vec<String>> {
+    std::env::var(key).ok().map(|v| {
+        v.split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(configure: impl FnOnce(&mut Config)) -> Result<(), String> {
+        let mut config = Config::default();
+        configure(&mut config);
+        config.validate().map_err(|e| e.key)
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(validate(|_| {}), Ok(()));
+    }
 
-/// Get the global configuration (initializes from env on first call).
-pub fn config() -> &'static Config {
-    CONFIG.get_or_init(Config::from_env)
+    #[test]
+    fn caps_max_retries() {
+        assert_eq!(validate(|c| c.max_retries = MAX_RETRIES), Ok(()));
+        assert_eq!(
+            validate(|c| c.max_retries = MAX_RETRIES + 1),
+            Err("max_retries".to_string())
+        );
+    }
+
+    #[test]
+    fn short_global_timeout_is_valid() {
+        assert_eq!(
+            validate(|c| c.timeout_global = Duration::from_secs(5)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn retry_base_delay_must_not_exceed_max() {
+        assert_eq!(
+            validate(|c| {
+                c.retry_base_delay = Duration::from_secs(2);
+                c.retry_max_delay = Duration::from_secs(1);
+            }),
+            Err("retry_base_delay_ms".to_string())
+        );
+        assert_eq!(
+            validate(|c| {
+                c.retry_base_delay = Duration::from_secs(1);
+                c.retry_max_delay = Duration::from_secs(1);
+            }),
+            Ok(())
+        );
+    }
 }
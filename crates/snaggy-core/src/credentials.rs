@@ -0,0 +1,597 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::config::Config;
+use crate::egress::host_matches;
+
+tokio::task_local! {
+    /// Host patterns of the credentials with custom headers, for the request
+    /// being sent. The client, and so its redirect policy, is shared by
+    /// scans with different credentials.
+    static HEADER_HOSTS: Vec<Vec<String>>;
+}
+
+/// Headers, cookies and credentials sent only to matching hosts, so logins
+/// for the scanned site never reach third-party CDNs.
+///
+/// Every matching entry applies, in order; later entries win for the same
+/// header. `Authorization` and `Cookie` are dropped when a redirect leaves
+/// the host, and redirects that would carry custom headers to a
+/// non-matching host are refused.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SiteCredentials {
+    /// Host patterns; `*.example.com` matches subdomains.
+    pub hosts: Vec<String>,
+    pub headers: BTreeMap<String, String>,
+    pub basic_auth: Option<BasicAuth>,
+    pub bearer_token: Option<String>,
+    /// Cookies sent to every matching host, by name.
+    pub cookies: BTreeMap<String, String>,
+    /// Netscape `cookies.txt` or JSON cookie export. Config file only.
+    pub cookies_file: Option<String>,
+    /// Cookies read from `cookies_file`.
+    #[serde(skip)]
+    pub(crate) jar: Vec<StoredCookie>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: Option<String>,
+}
+
+/// A cookie with its own domain, path and expiry, as exported by a browser.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StoredCookie {
+    domain: String,
+    include_subdomains: bool,
+    path: String,
+    secure: bool,
+    /// Unix seconds; `None` for session cookies.
+    expires: Option<u64>,
+    name: String,
+    value: String,
+}
+
+impl StoredCookie {
+    fn matches(&self, url: &Url, host: &str, now: u64) -> bool {
+        let domain_ok = host == self.domain
+            || (self.include_subdomains && host.ends_with(&format!(".{}", self.domain)));
+        domain_ok
+            && url.path().starts_with(&self.path)
+            && (!self.secure || url.scheme() == "https")
+            && self.expires.is_none_or(|t| t > now)
+    }
+}
+
+impl SiteCredentials {
+    pub(crate) fn matches(&self, host: &str) -> bool {
+        host_matches(host, &self.hosts)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.hosts.is_empty() {
+            return Err("hosts must list at least one host pattern".to_string());
+        }
+        for (name, value) in &self.headers {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("\"{}\" is not a valid header name", name))?;
+            HeaderValue::from_str(value)
+                .map_err(|_| format!("header \"{}\" has an invalid value", name))?;
+        }
+        for (name, value) in &self.cookies {
+            let valid = |s: &str| !s.contains([';', '\r', '\n']);
+            if name.is_empty() || name.contains('=') || !valid(name) || !valid(value) {
+                return Err(format!("cookie \"{}\" has an invalid name or value", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read `cookies_file` into the jar.
+    pub(crate) fn load_cookies(&mut self) -> Result<(), String> {
+        let Some(path) = &self.cookies_file else {
+            return Ok(());
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let is_json = Path::new(path)
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+            || text.trim_start().starts_with(['[', '{']);
+        self.jar = if is_json {
+            parse_json_cookies(&text).map_err(|e| format!("{}: {}", path, e))?
+        } else {
+            parse_netscape_cookies(&text).map_err(|e| format!("{}: {}", path, e))?
+        };
+        Ok(())
+    }
+}
+
+/// Parse a Netscape/curl `cookies.txt`: tab-separated domain, subdomain
+/// flag, path, secure flag, expiry, name and value.
+fn parse_netscape_cookies(text: &str) -> Result<Vec<StoredCookie>, String> {
+    let mut cookies = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        // curl marks HttpOnly cookies with a prefix on an otherwise commented line.
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+            return Err(format!("line {}: expected 7 tab-separated fields", n + 1));
+        };
+        let expires: u64 = expires
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {}: invalid expiry \"{}\"", n + 1, expires))?;
+        cookies.push(StoredCookie {
+            domain: domain.trim_start_matches('.').to_lowercase(),
+            include_subdomains: subdomains.eq_ignore_ascii_case("TRUE") || domain.starts_with('.'),
+            path: path.to_string(),
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            expires: (expires > 0).then_some(expires),
+            name: name.to_string(),
+            value: value.trim_end_matches('\r').to_string(),
+        });
+    }
+    Ok(cookies)
+}
+
+/// A cookie as written by browser extensions (`expirationDate`, `hostOnly`)
+/// or Playwright/Puppeteer (`expires`).
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonCookie {
+    domain: String,
+    name: String,
+    value: String,
+    #[serde(default = "root_path")]
+    path: String,
+    #[serde(default)]
+    secure: bool,
+    host_only: Option<bool>,
+    #[serde(alias = "expires")]
+    expiration_date: Option<f64>,
+}
+
+fn root_path() -> String {
+    "/".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonCookies {
+    List(Vec<JsonCookie>),
+    /// Playwright `storageState` files.
+    State {
+        cookies: Vec<JsonCookie>,
+    },
+}
+
+/// Parse a JSON cookie export: an array of cookies or `{ "cookies": [...] }`.
+fn parse_json_cookies(text: &str) -> Result<Vec<StoredCookie>, String> {
+    let cookies = match serde_json::from_str(text).map_err(|e| e.to_string())? {
+        JsonCookies::List(cookies) | JsonCookies::State { cookies } => cookies,
+    };
+    Ok(cookies
+        .into_iter()
+        .map(|c| StoredCookie {
+            include_subdomains: c.host_only.map_or(c.domain.starts_with('.'), |h| !h),
+            domain: c.domain.trim_start_matches('.').to_lowercase(),
+            path: c.path,
+            secure: c.secure,
+            // Session cookies are exported as -1 (or 0).
+            expires: c.expiration_date.filter(|&t| t > 0.0).map(|t| t as u64),
+            name: c.name,
+            value: c.value,
+        })
+        .collect())
+}
+
+/// The URL's host as credentials are matched against it: lowercase,
+/// without a trailing dot.
+fn normalized_host(url: &Url) -> Option<String> {
+    url.host_str()
+        .map(|h| h.trim_end_matches('.').to_lowercase())
+}
+
+/// Add the user agent, and the headers, cookies and credentials configured
+/// for `url`'s host. The user agent is set per request so scans with their
+/// own share the pooled client.
+fn authorize(cfg: &Config, request: RequestBuilder, url: &str) -> RequestBuilder {
+    let mut request = request.header(USER_AGENT, &cfg.user_agent);
+    let Ok(url) = Url::parse(url) else {
+        return request;
+    };
+    let Some(host) = normalized_host(&url) else {
+        return request;
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut headers = HeaderMap::new();
+    let mut cookies = BTreeMap::new();
+    for site in cfg.credentials.iter().filter(|s| s.matches(&host)) {
+        for (name, value) in &site.headers {
+            // Validated when the credentials were loaded.
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+        if let Some(auth) = &site.basic_auth {
+            request = request.basic_auth(&auth.username, auth.password.as_ref());
+        }
+        if let Some(token) = &site.bearer_token {
+            request = request.bearer_auth(token);
+        }
+        for cookie in site.jar.iter().filter(|c| c.matches(&url, &host, now)) {
+            cookies.insert(cookie.name.clone(), cookie.value.clone());
+        }
+        cookies.extend(site.cookies.clone());
+    }
+
+    request = request.headers(headers);
+    if !cookies.is_empty() {
+        let header: Vec<String> = cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        request = request.header(reqwest::header::COOKIE, header.join("; "));
+    }
+    request
+}
+
+/// A GET request with the configured user agent and any credentials
+/// configured for the URL's host.
+pub(crate) fn get(cfg: &Config, client: &Client, url: &str) -> RequestBuilder {
+    authorize(cfg, client.get(url), url)
+}
+
+/// A HEAD request with the configured user agent and any credentials
+/// configured for the URL's host.
+pub(crate) fn head(cfg: &Config, client: &Client, url: &str) -> RequestBuilder {
+    authorize(cfg, client.head(url), url)
+}
+
+fn header_hosts(cfg: &Config) -> Vec<Vec<String>> {
+    cfg.credentials
+        .iter()
+        .filter(|site| !site.headers.is_empty())
+        .map(|site| site.hosts.clone())
+        .collect()
+}
+
+/// Send a request with `cfg`'s credentials visible to [`leaks_headers`].
+pub(crate) async fn scoped<F: Future>(cfg: &Config, send: F) -> F::Output {
+    HEADER_HOSTS.scope(header_hosts(cfg), send).await
+}
+
+/// Whether following a redirect from `from` to `to` would send custom
+/// headers to a host they were not configured for. Uses the credentials of
+/// the request being sent (see [`scoped`]), else those of `cfg`.
+pub(crate) fn leaks_headers(cfg: &Config, from: &Url, to: &Url) -> bool {
+    let (Some(from), Some(to)) = (normalized_host(from), normalized_host(to)) else {
+        return false;
+    };
+    let leaks = |sites: &Vec<Vec<String>>| {
+        sites
+            .iter()
+            .any(|hosts| host_matches(&from, hosts) && !host_matches(&to, hosts))
+    };
+    HEADER_HOSTS
+        .try_with(leaks)
+        .unwrap_or_else(|_| leaks(&header_hosts(cfg)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ScanOptions;
+    use crate::retry;
+    use crate::scanner::Scanner;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn cookie(domain: &str, name: &str, value: &str) -> StoredCookie {
+        StoredCookie {
+            domain: domain.to_string(),
+            include_subdomains: false,
+            path: "/".to_string(),
+            secure: false,
+            expires: None,
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_netscape_cookies() {
+        let text = "# Netscape HTTP Cookie File\n\
+                    \n\
+                    .example.com\tTRUE\t/\tFALSE\t0\tsession\tabc\n\
+                    #HttpOnly_Staging.Example.com\tFALSE\t/app\tTRUE\t4102444800\tauth\tx=y\r\n\
+                    # a comment\tTRUE\t/\tFALSE\t0\tname\tvalue\n";
+        let cookies = parse_netscape_cookies(text).unwrap();
+        assert_eq!(
+            cookies,
+            vec![
+                StoredCookie {
+                    include_subdomains: true,
+                    value: "abc".to_string(),
+                    ..cookie("example.com", "session", "")
+                },
+                StoredCookie {
+                    path: "/app".to_string(),
+                    secure: true,
+                    expires: Some(4102444800),
+                    ..cookie("staging.example.com", "auth", "x=y")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn netscape_subdomain_flag_follows_leading_dot() {
+        let cookies = parse_netscape_cookies(".example.com\tFALSE\t/\tFALSE\t0\ta\tb").unwrap();
+        assert!(cookies[0].include_subdomains);
+        let cookies = parse_netscape_cookies("example.com\tTRUE\t/\tFALSE\t0\ta\tb").unwrap();
+        assert!(cookies[0].include_subdomains);
+    }
+
+    #[test]
+    fn netscape_allows_empty_values() {
+        let cookies = parse_netscape_cookies("example.com\tFALSE\t/\tFALSE\t0\tflag\t").unwrap();
+        assert_eq!(cookies, vec![cookie("example.com", "flag", "")]);
+    }
+
+    #[test]
+    fn rejects_malformed_netscape_lines() {
+        let six = "example.com\tFALSE\t/\tFALSE\t0\tname";
+        assert_eq!(
+            parse_netscape_cookies(six).unwrap_err(),
+            "line 1: expected 7 tab-separated fields"
+        );
+        let eight = "\nexample.com\tFALSE\t/\tFALSE\t0\tname\tvalue\textra";
+        assert_eq!(
+            parse_netscape_cookies(eight).unwrap_err(),
+            "line 2: expected 7 tab-separated fields"
+        );
+        let spaces = "example.com FALSE / FALSE 0 name value";
+        assert!(parse_netscape_cookies(spaces).is_err());
+        let expiry = "example.com\tFALSE\t/\tFALSE\tsoon\tname\tvalue";
+        assert_eq!(
+            parse_netscape_cookies(expiry).unwrap_err(),
+            "line 1: invalid expiry \"soon\""
+        );
+    }
+
+    #[test]
+    fn parses_json_cookie_lists() {
+        let text = r#"[
+            {"domain": ".Example.com", "name": "a", "value": "1", "expirationDate": 4102444800.5},
+            {"domain": "example.com", "name": "b", "value": "2", "hostOnly": false, "path": "/x", "secure": true},
+            {"domain": ".example.com", "name": "c", "value": "3", "hostOnly": true, "expires": -1}
+        ]"#;
+        let cookies = parse_json_cookies(text).unwrap();
+        assert_eq!(
+            cookies,
+            vec![
+                StoredCookie {
+                    include_subdomains: true,
+                    expires: Some(4102444800),
+                    ..cookie("example.com", "a", "1")
+                },
+                StoredCookie {
+                    include_subdomains: true,
+                    path: "/x".to_string(),
+                    secure: true,
+                    ..cookie("example.com", "b", "2")
+                },
+                cookie("example.com", "c", "3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_playwright_storage_state() {
+        let text = r#"{"cookies": [{"domain": "example.com", "name": "a", "value": "1", "expires": 0}], "origins": []}"#;
+        assert_eq!(
+            parse_json_cookies(text).unwrap(),
+            vec![cookie("example.com", "a", "1")]
+        );
+        assert!(parse_json_cookies(r#"[{"name": "a", "value": "1"}]"#).is_err());
+    }
+
+    #[test]
+    fn stored_cookies_match_domain_path_scheme_and_expiry() {
+        let url = Url::parse("http://www.example.com/app/page").unwrap();
+        let now = 1_000;
+        let base = cookie("example.com", "a", "1");
+        assert!(!base.matches(&url, "www.example.com", now));
+        let wide = StoredCookie {
+            include_subdomains: true,
+            ..base.clone()
+        };
+        assert!(wide.matches(&url, "www.example.com", now));
+        assert!(!wide.matches(&url, "www.notexample.com", now));
+        let secure = StoredCookie {
+            secure: true,
+            ..wide.clone()
+        };
+        assert!(!secure.matches(&url, "www.example.com", now));
+        let expired = StoredCookie {
+            expires: Some(now),
+            ..wide.clone()
+        };
+        assert!(!expired.matches(&url, "www.example.com", now));
+        let other_path = StoredCookie {
+            path: "/admin".to_string(),
+            ..wide
+        };
+        assert!(!other_path.matches(&url, "www.example.com", now));
+    }
+
+    fn with_headers(hosts: &[&str]) -> SiteCredentials {
+        SiteCredentials {
+            hosts: hosts.iter().map(|h| h.to_string()).collect(),
+            headers: BTreeMap::from([("X-Token".to_string(), "secret".to_string())]),
+            ..SiteCredentials::default()
+        }
+    }
+
+    #[test]
+    fn leaks_headers_normalizes_hosts() {
+        let cfg = Config {
+            credentials: vec![with_headers(&["staging.example.com"])],
+            ..Config::default()
+        };
+        let url = |s: &str| Url::parse(s).unwrap();
+        let from = url("https://Staging.Example.com./");
+        assert!(!leaks_headers(
+            &cfg,
+            &from,
+            &url("https://STAGING.example.com/next")
+        ));
+        assert!(!leaks_headers(
+            &cfg,
+            &from,
+            &url("https://staging.example.com./")
+        ));
+        assert!(leaks_headers(&cfg, &from, &url("https://cdn.example.net/")));
+        assert!(!leaks_headers(
+            &cfg,
+            &url("https://other.example/"),
+            &url("https://cdn.example.net/")
+        ));
+    }
+
+    /// Redirects `/` to `/done` on `target`, and answers `/done` with 200.
+    async fn redirect_server(target: &'static str) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let response = if request.starts_with("GET /done") {
+                    "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok"
+                        .to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 302 Found\r\nlocation: http://{}:{}/done\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                        target, port
+                    )
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn shared_client_uses_each_scans_credentials_for_redirects() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let port = redirect_server("127.0.0.1").await;
+            let scanner = Scanner::new(Config {
+                allow_private_networks: true,
+                max_retries: 0,
+                ..Config::default()
+            })
+            .unwrap();
+            let url = format!("http://localhost:{}/", port);
+
+            let fetch = |credentials: Vec<SiteCredentials>| {
+                let scanner = scanner
+                    .with_options(&ScanOptions {
+                        credentials,
+                        ..ScanOptions::default()
+                    })
+                    .unwrap();
+                let url = url.clone();
+                async move {
+                    let request = get(scanner.config(), scanner.client(), &url);
+                    retry::send(scanner.config(), request).await.0
+                }
+            };
+
+            let response = fetch(vec![with_headers(&["*.example.com"])]).await.unwrap();
+            assert_eq!(response.status(), 200);
+            let error = fetch(vec![with_headers(&["localhost"])]).await.unwrap_err();
+            assert!(error.is_redirect(), "{}", error);
+        });
+    }
+
+    #[test]
+    fn shared_client_sends_each_scans_user_agent() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // Answers with the request's User-Agent.
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            tokio::spawn(async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let mut buf = [0; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                    let agent = request
+                        .lines()
+                        .find_map(|l| l.strip_prefix("user-agent: "))
+                        .unwrap_or_default()
+                        .to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        agent.len(),
+                        agent
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                }
+            });
+
+            let scanner = Scanner::new(Config {
+                allow_private_networks: true,
+                user_agent: "base/1.0".to_string(),
+                ..Config::default()
+            })
+            .unwrap();
+            let agent_of = |scanner: Scanner| {
+                let url = url.clone();
+                async move {
+                    let request = get(scanner.config(), scanner.client(), &url);
+                    let response = retry::send(scanner.config(), request).await.0.unwrap();
+                    response.text().await.unwrap()
+                }
+            };
+
+            let custom = scanner
+                .with_options(&ScanOptions {
+                    user_agent: Some("custom/2.0".to_string()),
+                    ..ScanOptions::default()
+                })
+                .unwrap();
+            assert_eq!(agent_of(custom).await, "custom/2.0");
+            assert_eq!(agent_of(scanner).await, "base/1.0");
+        });
+    }
+}
@@ -0,0 +1,649 @@
+use futures::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::credentials;
+use crate::diagnostics::{Diagnostic, Trace};
+use crate::error::ScanError;
+use crate::probe::sniff_image_type;
+use crate::scanner::{FaviconInfo, ScanWarning};
+
+/// What an icon file actually contains, as opposed to what the page claims.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconDetails {
+    /// MIME type verified from the file's magic bytes.
+    pub mime_type: String,
+    /// Pixel dimensions (the largest frame for ICO/ICNS). `None` for SVGs
+    /// without explicit width/height or viewBox.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Bits per pixel, where the format records it.
+    pub bit_depth: Option<u16>,
+    pub file_size: u64,
+    /// Embedded frames for ICO/ICNS containers; empty for single images.
+    pub frames: Vec<IconFrame>,
+    /// True when the declared `sizes` don't match any actual dimensions.
+    pub size_mismatch: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconFrame {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: Option<u16>,
+    /// Frame encoding: "png" or "bmp" for ICO, the OSType for ICNS.
+    pub format: String,
+}
+
+/// Icon containers hold at most 1024px (ICNS) or 256px (ICO) frames; larger
+/// embedded PNG headers are treated as corrupt or hostile.
+const MAX_FRAME_DIMENSION: u32 = 16_384;
+
+fn plausible(frame: &IconFrame) -> bool {
+    (1..=MAX_FRAME_DIMENSION).contains(&frame.width)
+        && (1..=MAX_FRAME_DIMENSION).contains(&frame.height)
+}
+
+fn u16_le(b: &[u8], at: usize) -> Option<u16> {
+    b.get(at..at + 2).map(|s| u16::from_le_bytes([s[0], s[1]]))
+}
+
+fn u16_be(b: &[u8], at: usize) -> Option<u16> {
+    b.get(at..at + 2).map(|s| u16::from_be_bytes([s[0], s[1]]))
+}
+
+fn u32_le(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 4)
+        .map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+fn u32_be(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 4)
+        .map(|s| u32::from_be_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+fn u24_le(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 3)
+        .map(|s| u32::from_le_bytes([s[0], s[1], s[2], 0]))
+}
+
+/// Width, height and bits per pixel from a PNG's IHDR chunk.
+fn png_dims(b: &[u8]) -> Option<(u32, u32, Option<u16>)> {
+    if !b.starts_with(b"\x89PNG\r\n\x1a\n") || b.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32_be(b, 16)?;
+    let height = u32_be(b, 20)?;
+    let depth = *b.get(24)? as u16;
+    let channels = match *b.get(25)? {
+        0 | 3 => 1,
+        4 => 2,
+        2 => 3,
+        6 => 4,
+        _ => 0,
+    };
+    let bpp = (channels > 0).then_some(depth * channels);
+    Some((width, height, bpp))
+}
+
+fn gif_dims(b: &[u8]) -> Option<(u32, u32, Option<u16>)> {
+    let width = u16_le(b, 6)? as u32;
+    let height = u16_le(b, 8)? as u32;
+    let packed = *b.get(10)?;
+    let bpp = (packed & 0x80 != 0).then_some((packed & 0x07) as u16 + 1);
+    Some((width, height, bpp))
+}
+
+/// Walk JPEG markers until a start-of-frame segment.
+fn jpeg_dims(b: &[u8]) -> Option<(u32, u32, Option<u16>)> {
+    let mut i = 2;
+    while i + 4 <= b.len() {
+        if b[i] != 0xFF {
+            return None;
+        }
+        let marker = b[i + 1];
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        if matches!(marker, 0xD0..=0xD9 | 0x01) {
+            i += 2;
+            continue;
+        }
+        let len = u16_be(b, i + 2)? as usize;
+        let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_sof {
+            let precision = *b.get(i + 4)? as u16;
+            let height = u16_be(b, i + 5)? as u32;
+            let width = u16_be(b, i + 7)? as u32;
+            let components = *b.get(i + 9)? as u16;
+            return Some((width, height, Some(precision * components)));
+        }
+        i += 2 + len;
+    }
+    None
+}
+
+fn webp_dims(b: &[u8]) -> Option<(u32, u32, Option<u16>)> {
+    match b.get(12..16)? {
+        b"VP8 " => {
+            let width = (u16_le(b, 26)? & 0x3FFF) as u32;
+            let height = (u16_le(b, 28)? & 0x3FFF) as u32;
+            Some((width, height, Some(24)))
+        }
+        b"VP8L" => {
+            let bits = u32_le(b, 21)?;
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            let has_alpha = (bits >> 28) & 1 == 1;
+            Some((width, height, Some(if has_alpha { 32 } else { 24 })))
+        }
+        b"VP8X" => {
+            let has_alpha = *b.get(20)? & 0x10 != 0;
+            let width = u24_le(b, 24)? + 1;
+            let height = u24_le(b, 27)? + 1;
+            Some((width, height, Some(if has_alpha { 32 } else { 24 })))
+        }
+        _ => None,
+    }
+}
+
+fn bmp_dims(b: &[u8]) -> Option<(u32, u32, Option<u16>)> {
+    let width = u32_le(b, 18)? as i32;
+    let height = u32_le(b, 22)? as i32;
+    let bpp = u16_le(b, 28)?;
+    Some((width.unsigned_abs(), height.unsigned_abs(), Some(bpp)))
+}
+
+/// Parse an SVG length like "32", "32px" or "32.5"; relative units are ignored.
+fn svg_length(value: &str) -> Option<u32> {
+    let v = value.trim();
+    let v = v.strip_suffix("px").unwrap_or(v);
+    v.parse::<f32>()
+        .ok()
+        .filter(|n| *n > 0.0)
+        .map(|n| n.round() as u32)
+}
+
+fn svg_dims(b: &[u8]) -> Option<(u32, u32)> {
+    let text = std::str::from_utf8(b).ok()?;
+    let doc = roxmltree::Document::parse(text).ok()?;
+    let root = doc.root_element();
+
+    let width = root.attribute("width").and_then(svg_length);
+    let height = root.attribute("height").and_then(svg_length);
+    if let (Some(w), Some(h)) = (width, height) {
+        return Some((w, h));
+    }
+
+    let view_box: Vec<f32> = root
+        .attribute("viewBox")?
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| s.parse().ok())
+        .collect();
+    match view_box.as_slice() {
+        [_, _, w, h] if *w > 0.0 && *h > 0.0 => Some((w.round() as u32, h.round() as u32)),
+        _ => None,
+    }
+}
+
+/// ICO/CUR directory entries. Frames stored as PNG report their IHDR values,
+/// which are authoritative over the directory's 8-bit width/height fields.
+fn ico_frames(b: &[u8]) -> Vec<IconFrame> {
+    let count = u16_le(b, 4).unwrap_or(0) as usize;
+    let mut frames = Vec::new();
+
+    for n in 0..count {
+        let entry = 6 + n * 16;
+        let (Some(&w), Some(&h), Some(bpp), Some(offset)) = (
+            b.get(entry),
+            b.get(entry + 1),
+            u16_le(b, entry + 6),
+            u32_le(b, entry + 12),
+        ) else {
+            break;
+        };
+        let data = b.get(offset as usize..).unwrap_or_default();
+
+        if let Some((width, height, png_bpp)) = png_dims(data) {
+            frames.push(IconFrame {
+                width,
+                height,
+                bit_depth: png_bpp,
+                format: "png".to_string(),
+            });
+            continue;
+        }
+
+        let dib_bpp = u16_le(data, 14).filter(|v| *v > 0);
+        frames.push(IconFrame {
+            width: if w == 0 { 256 } else { w as u32 },
+            height: if h == 0 { 256 } else { h as u32 },
+            bit_depth: if bpp > 0 { Some(bpp) } else { dib_bpp },
+            format: "bmp".to_string(),
+        });
+    }
+
+    frames
+}
+
+/// Nominal pixel size of each ICNS image OSType (masks and metadata omitted).
+const ICNS_TYPES: &[(&[u8; 4], u32)] = &[
+    (b"ics#", 16),
+    (b"is32", 16),
+    (b"icp4", 16),
+    (b"ICN#", 32),
+    (b"il32", 32),
+    (b"icp5", 32),
+    (b"ic11", 32),
+    (b"ich#", 48),
+    (b"ih32", 48),
+    (b"icp6", 64),
+    (b"ic12", 64),
+    (b"it32", 128),
+    (b"ic07", 128),
+    (b"ic08", 256),
+    (b"ic13", 256),
+    (b"ic09", 512),
+    (b"ic14", 512),
+    (b"ic10", 1024),
+];
+
+fn icns_frames(b: &[u8]) -> Vec<IconFrame> {
+    let total = u32_be(b, 4).map(|t| t as usize).unwrap_or(0).min(b.len());
+    let mut frames = Vec::new();
+    let mut i = 8;
+
+    while i + 8 <= total {
+        let os_type = &b[i..i + 4];
+        let len = match u32_be(b, i + 4) {
+            Some(l) if l >= 8 => l as usize,
+            _ => break,
+        };
+        let data = b.get(i + 8..(i + len).min(b.len())).unwrap_or_default();
+
+        if let Some((_, size)) = ICNS_TYPES.iter().find(|(t, _)| t.as_slice() == os_type) {
+            let format = String::from_utf8_lossy(os_type).to_string();
+            let frame = match png_dims(data) {
+                Some((width, height, bpp)) => IconFrame {
+                    width,
+                    height,
+                    bit_depth: bpp,
+                    format,
+                },
+                None => IconFrame {
+                    width: *size,
+                    height: *size,
+                    bit_depth: None,
+                    format,
+                },
+            };
+            frames.push(frame);
+        }
+
+        i += len;
+    }
+
+    frames
+}
+
+/// Parse declared sizes like "16x16 32X32" into pixel pairs, ignoring "any".
+fn declared_sizes(sizes: &str) -> Vec<(u32, u32)> {
+    sizes
+        .split_whitespace()
+        .filter_map(|token| {
+            let (w, h) = token
+                .to_lowercase()
+                .split_once('x')
+                .map(|(w, h)| (w.parse::<u32>(), h.parse::<u32>()))?;
+            Some((w.ok()?, h.ok()?))
+        })
+        .collect()
+}
+
+/// Decode an icon file's headers. Returns `None` for unrecognised formats.
+pub fn decode_icon(bytes: &[u8], declared: Option<&str>) -> Option<IconDetails> {
+    let mime_type = sniff_image_type(bytes)?;
+
+    let mut frames = Vec::new();
+    let (width, height, bit_depth) = match mime_type {
+        "image/x-icon" | "image/x-win-bitmap" | "image/icns" => {
+            frames = if mime_type == "image/icns" {
+                icns_frames(bytes)
+            } else {
+                ico_frames(bytes)
+            };
+            frames.retain(plausible);
+            match frames
+                .iter()
+                .max_by_key(|f| (u64::from(f.width) * u64::from(f.height), f.bit_depth))
+            {
+                Some(f) => (Some(f.width), Some(f.height), f.bit_depth),
+                None => (None, None, None),
+            }
+        }
+        "image/svg+xml" => match svg_dims(bytes) {
+            Some((w, h)) => (Some(w), Some(h), None),
+            None => (None, None, None),
+        },
+        _ => {
+            let dims = match mime_type {
+                "image/png" => png_dims(bytes),
+                "image/gif" => gif_dims(bytes),
+                "image/jpeg" => jpeg_dims(bytes),
+                "image/webp" => webp_dims(bytes),
+                "image/bmp" => bmp_dims(bytes),
+                _ => None,
+            };
+            match dims {
+                Some((w, h, bpp)) => (Some(w), Some(h), bpp),
+                None => (None, None, None),
+            }
+        }
+    };
+
+    // SVGs scale to any declared size, so only raster formats can mismatch.
+    let size_mismatch = match declared {
+        Some(d) if mime_type != "image/svg+xml" => {
+            let actual: Vec<(u32, u32)> = if frames.is_empty() {
+                width.zip(height).into_iter().collect()
+            } else {
+                frames.iter().map(|f| (f.width, f.height)).collect()
+            };
+            declared_sizes(d).iter().any(|s| !actual.contains(s))
+        }
+        _ => false,
+    };
+
+    Some(IconDetails {
+        mime_type: mime_type.to_string(),
+        width,
+        height,
+        bit_depth,
+        file_size: bytes.len() as u64,
+        frames,
+        size_mismatch,
+    })
+}
+
+/// Fetch and decode one icon. Icons over `max_image_size` are skipped with
+/// a warning, since a partial file can't be decoded reliably.
+async fn fetch_details(
+    cfg: &Config,
+    favicon: &FaviconInfo,
+    client: &reqwest::Client,
+    trace: &mut Trace,
+) -> (Option<IconDetails>, Option<ScanWarning>) {
+    if !trace.allowed(cfg).await {
+        return (None, None);
+    }
+    let Some(resp) = trace
+        .send(
+            cfg,
+            credentials::get(cfg, client, &favicon.url).timeout(cfg.timeout_image),
+        )
+        .await
+    else {
+        return (None, None);
+    };
+
+    match trace.read(resp, cfg.max_image_size).await {
+        Some(body) if body.truncated => {
+            trace.fail(ScanError::TooLarge {
+                limit: cfg.max_image_size,
+            });
+            (
+                None,
+                Some(ScanWarning::too_large(
+                    "image",
+                    &favicon.url,
+                    cfg.max_image_size,
+                )),
+            )
+        }
+        Some(body) => {
+            trace.parsed(body.bytes.len());
+            let details = decode_icon(&body.bytes, favicon.sizes.as_deref());
+            if details.is_none() {
+                trace.fail(ScanError::Parse("Not a recognized image".to_string()));
+            }
+            (details, None)
+        }
+        None => (None, None),
+    }
+}
+
+/// Fetch the icons, `download_concurrency` at a time, and attach decoded
+/// details where possible.
+pub(crate) async fn inspect_favicons(
+    cfg: &Config,
+    favicons: &mut [FaviconInfo],
+    client: &reqwest::Client,
+) -> (Vec<ScanWarning>, Vec<Diagnostic>) {
+    // Collected first: a stream mapping over borrowed icons trips up the
+    // `Send` check on handlers that await this.
+    let fetches: Vec<_> = favicons
+        .iter()
+        .map(|f| async move {
+            let mut trace = Trace::new("icon", &f.url);
+            let (details, warning) = fetch_details(cfg, f, client, &mut trace).await;
+            (details, warning, trace.finish())
+        })
+        .collect();
+    let results: Vec<_> = stream::iter(fetches)
+        .buffered(cfg.download_concurrency)
+        .collect()
+        .await;
+
+    let mut warnings = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (favicon, (details, warning, diagnostic)) in favicons.iter_mut().zip(results) {
+        favicon.details = details;
+        warnings.extend(warning);
+        diagnostics.push(diagnostic);
+    }
+    (warnings, diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        png
+    }
+
+    /// An ICO whose entries are `(width, height, bpp, data)`.
+    fn ico(entries: &[(u8, u8, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut ico = vec![0, 0, 1, 0];
+        ico.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        let mut offset = 6 + 16 * entries.len() as u32;
+        let mut data = Vec::new();
+        for (w, h, bpp, body) in entries {
+            ico.extend_from_slice(&[*w, *h, 0, 0, 1, 0]);
+            ico.extend_from_slice(&bpp.to_le_bytes());
+            ico.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            ico.extend_from_slice(&offset.to_le_bytes());
+            offset += body.len() as u32;
+            data.extend_from_slice(body);
+        }
+        ico.extend(data);
+        ico
+    }
+
+    fn icns(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (os_type, data) in chunks {
+            body.extend_from_slice(*os_type);
+            body.extend_from_slice(&(data.len() as u32 + 8).to_be_bytes());
+            body.extend_from_slice(data);
+        }
+        let mut icns = b"icns".to_vec();
+        icns.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+        icns.extend(body);
+        icns
+    }
+
+    #[test]
+    fn ico_reports_the_largest_frame() {
+        let file = ico(&[
+            (16, 16, 32, vec![0; 40]),
+            (0, 0, 32, png_header(256, 256)),
+            (32, 32, 8, vec![0; 40]),
+        ]);
+        let details = decode_icon(&file, Some("16x16 32x32 256x256")).unwrap();
+        assert_eq!(details.mime_type, "image/x-icon");
+        assert_eq!(details.frames.len(), 3);
+        assert_eq!((details.width, details.height), (Some(256), Some(256)));
+        assert_eq!(details.frames[1].format, "png");
+        assert!(!details.size_mismatch);
+    }
+
+    #[test]
+    fn ico_zero_size_means_256() {
+        let details = decode_icon(&ico(&[(0, 0, 32, vec![0; 40])]), None).unwrap();
+        assert_eq!(details.width, Some(256));
+    }
+
+    #[test]
+    fn ico_drops_oversized_png_frames() {
+        let file = ico(&[
+            (48, 48, 32, vec![0; 40]),
+            (0, 0, 32, png_header(65_536, 65_536)),
+            (0, 0, 32, png_header(u32::MAX, 2)),
+        ]);
+        let details = decode_icon(&file, None).unwrap();
+        assert_eq!(details.frames.len(), 1);
+        assert_eq!(details.width, Some(48));
+    }
+
+    #[test]
+    fn ico_with_truncated_directory_keeps_complete_entries() {
+        let mut file = ico(&[(16, 16, 32, vec![0; 40])]);
+        // Claim far more entries than the file holds.
+        file[4..6].copy_from_slice(&u16::MAX.to_le_bytes());
+        let details = decode_icon(&file, None).unwrap();
+        assert!(details.frames.len() <= 4);
+        assert!(details.frames.iter().all(|f| f.width <= 256));
+    }
+
+    #[test]
+    fn ico_frame_offset_past_the_end_is_a_bmp_frame() {
+        let mut file = ico(&[(16, 16, 0, vec![])]);
+        file[18..22].copy_from_slice(&u32::MAX.to_le_bytes());
+        let details = decode_icon(&file, Some("32x32")).unwrap();
+        assert_eq!(details.frames[0].format, "bmp");
+        assert_eq!(details.frames[0].bit_depth, None);
+        assert!(details.size_mismatch);
+    }
+
+    #[test]
+    fn icns_reads_png_and_legacy_frames() {
+        let file = icns(&[
+            (b"is32", vec![0; 16]),
+            (b"ic10", png_header(1024, 1024)),
+            (b"TOC ", vec![0; 8]),
+        ]);
+        let details = decode_icon(&file, None).unwrap();
+        assert_eq!(details.mime_type, "image/icns");
+        let sizes: Vec<_> = details.frames.iter().map(|f| f.width).collect();
+        assert_eq!(sizes, [16, 1024]);
+        assert_eq!(details.width, Some(1024));
+    }
+
+    #[test]
+    fn icns_stops_at_bad_chunk_lengths() {
+        let mut file = icns(&[(b"il32", vec![0; 16]), (b"ih32", vec![0; 16])]);
+        // A chunk length below the 8-byte header would never advance.
+        file[12..16].copy_from_slice(&4u32.to_be_bytes());
+        assert!(decode_icon(&file, None).unwrap().frames.is_empty());
+
+        let mut file = icns(&[(b"ic08", png_header(256, 256))]);
+        file[12..16].copy_from_slice(&u32::MAX.to_be_bytes());
+        let details = decode_icon(&file, None).unwrap();
+        assert_eq!(details.frames.len(), 1);
+    }
+
+    #[test]
+    fn icns_drops_oversized_png_frames() {
+        let file = icns(&[
+            (b"ic07", png_header(128, 128)),
+            (b"ic10", png_header(100_000, 100_000)),
+        ]);
+        let details = decode_icon(&file, None).unwrap();
+        assert_eq!(details.width, Some(128));
+    }
+
+    #[test]
+    fn declared_sizes_ignore_junk() {
+        assert_eq!(declared_sizes("16x16 any 32X32 x 9x"), [(16, 16), (32, 32)]);
+    }
+
+    #[test]
+    fn icons_are_fetched_download_concurrency_at_a_time() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            let (active, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+            let (a, p) = (active.clone(), peak.clone());
+            tokio::spawn(async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let (a, p) = (a.clone(), p.clone());
+                    tokio::spawn(async move {
+                        let mut buf = [0; 4096];
+                        let _ = socket.read(&mut buf).await;
+                        p.fetch_max(a.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+                        a.fetch_sub(1, Ordering::SeqCst);
+                        let body = png_header(16, 16);
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                            body.len()
+                        )
+                        .into_bytes();
+                        response.extend(body);
+                        let _ = socket.write_all(&response).await;
+                    });
+                }
+            });
+
+            let scanner = crate::scanner::Scanner::new(Config {
+                allow_private_networks: true,
+                download_concurrency: 2,
+                ..Config::default()
+            })
+            .unwrap();
+            let mut favicons: Vec<FaviconInfo> = (0..6)
+                .map(|i| FaviconInfo {
+                    url: format!("{}/icon-{}.png", base, i),
+                    rel: "icon".to_string(),
+                    sizes: None,
+                    mime_type: None,
+                    media: None,
+                    color: None,
+                    purpose: None,
+                    source: "html".to_string(),
+                    details: None,
+                })
+                .collect();
+            let (_, diagnostics) =
+                inspect_favicons(scanner.config(), &mut favicons, scanner.client()).await;
+            assert_eq!(diagnostics.len(), 6);
+            assert!(favicons.iter().all(|f| f.details.is_some()));
+            assert_eq!(peak.load(Ordering::SeqCst), 2);
+        });
+    }
+}
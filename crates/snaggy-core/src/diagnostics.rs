@@ -0,0 +1,155 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::download::{LimitedBytes, read_limited};
+use crate::egress::check_url_str;
+use crate::error::ScanError;
+use crate::retry;
+
+/// One request made while scanning, successful or not.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// "html", "css", "manifest", "browserconfig", "icon-probe" or "icon".
+    pub resource: String,
+    pub url: String,
+    /// HTTP status, if a response arrived.
+    pub status: Option<u16>,
+    /// Extra attempts after timeouts, dropped connections or busy responses.
+    pub retries: u32,
+    /// Time from sending the request until the body was handled.
+    pub duration_ms: u64,
+    /// Body bytes received, after any decompression and up to the size
+    /// limit. Falls back to `Content-Length` when the body wasn't read;
+    /// compressed responses usually don't have one.
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    /// Bytes read and handed to the parser.
+    pub bytes_parsed: Option<u64>,
+    /// `@font-face` rules found (stylesheets only).
+    pub faces_found: Option<usize>,
+    /// Failure code, as in [`ScanError::code`].
+    pub error_code: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Records a [`Diagnostic`] while one resource is fetched and parsed.
+///
+/// The fetch helpers return `None` after recording why they failed, so
+/// callers can keep skipping failed sub-resources with `?`.
+pub(crate) struct Trace {
+    diagnostic: Diagnostic,
+    started: Instant,
+}
+
+impl Trace {
+    pub fn new(resource: &str, url: &str) -> Self {
+        Self {
+            diagnostic: Diagnostic {
+                resource: resource.to_string(),
+                url: url.to_string(),
+                ..Diagnostic::default()
+            },
+            started: Instant::now(),
+        }
+    }
+
+    /// Check the URL against the egress policy.
+    pub async fn allowed(&mut self, cfg: &Config) -> bool {
+        match check_url_str(cfg, &self.diagnostic.url).await {
+            Ok(()) => true,
+            Err(e) => {
+                self.fail(e);
+                false
+            }
+        }
+    }
+
+    /// Send a request (with retries), recording the response metadata.
+    /// Non-success statuses count as failures.
+    pub async fn send(
+        &mut self,
+        cfg: &Config,
+        request: reqwest::RequestBuilder,
+    ) -> Option<reqwest::Response> {
+        let (result, retries) = retry::send(cfg, request).await;
+        self.retried(retries);
+        let response = match result {
+            Ok(r) => r,
+            Err(e) => {
+                self.fail(ScanError::from_request(e));
+                return None;
+            }
+        };
+
+        self.response(&response);
+        if !response.status().is_success() {
+            self.fail(ScanError::upstream_status(
+                self.diagnostic.url.clone(),
+                response.status(),
+            ));
+            return None;
+        }
+        Some(response)
+    }
+
+    pub fn retried(&mut self, retries: u32) {
+        self.diagnostic.retries += retries;
+    }
+
+    /// Record a response's status, length and content type.
+    pub fn response(&mut self, response: &reqwest::Response) {
+        self.diagnostic.status = Some(response.status().as_u16());
+        self.diagnostic.size = response.content_length();
+        self.diagnostic.content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+    }
+
+    /// Read the body up to `max_size` bytes.
+    pub async fn read(
+        &mut self,
+        response: reqwest::Response,
+        max_size: u64,
+    ) -> Option<LimitedBytes> {
+        match read_limited(response, max_size).await {
+            Ok(body) => {
+                self.received(body.bytes.len());
+                Some(body)
+            }
+            Err(e) => {
+                self.fail(e);
+                None
+            }
+        }
+    }
+
+    /// Record how many body bytes arrived, replacing `Content-Length`.
+    pub fn received(&mut self, bytes: usize) {
+        self.diagnostic.size = Some(bytes as u64);
+    }
+
+    pub fn parsed(&mut self, bytes: usize) {
+        self.diagnostic.bytes_parsed = Some(bytes as u64);
+    }
+
+    pub fn faces_found(&mut self, count: usize) {
+        self.diagnostic.faces_found = Some(count);
+    }
+
+    /// Record why the resource was not used. Only the first failure is kept.
+    pub fn fail(&mut self, error: ScanError) {
+        if self.diagnostic.error.is_none() {
+            self.diagnostic.error_code = Some(error.code().to_string());
+            self.diagnostic.error = Some(error.to_string());
+        }
+    }
+
+    pub fn finish(mut self) -> Diagnostic {
+        self.diagnostic.duration_ms = self.started.elapsed().as_millis() as u64;
+        self.diagnostic
+    }
+}
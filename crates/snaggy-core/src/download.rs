@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::egress::check_url_str;
+use crate::error::ScanError;
+use crate::probe::sniff_image_type;
+use crate::retry;
+use crate::svg::{RenderOptions, prepare_image};
+
+/// Response body chunks, in order.
+pub type ByteStream = BoxStream<'static, Result<Vec<u8>, ScanError>>;
+
+/// Progress of a running download, reported after every chunk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    pub url: String,
+    pub downloaded: u64,
+    /// From `Content-Length`, when the server sent one.
+    pub total: Option<u64>,
+}
+
+pub type ProgressCallback = Arc<dyn Fn(&DownloadProgress) + Send + Sync>;
+
+/// A response body read up to a size limit.
+pub(crate) struct LimitedBytes {
+    pub bytes: Vec<u8>,
+    /// The body was longer than the limit; `bytes` holds only its start.
+    pub truncated: bool,
+}
+
+/// Read at most `max_size` bytes of a response body.
+pub(crate) async fn read_limited(
+    mut response: reqwest::Response,
+    max_size: u64,
+) -> Result<LimitedBytes, ScanError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(ScanError::from_request)? {
+        let room = max_size.saturating_sub(bytes.len() as u64) as usize;
+        if chunk.len() > room {
+            bytes.extend_from_slice(&chunk[..room]);
+            return Ok(LimitedBytes {
+                bytes,
+                truncated: true,
+            });
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(LimitedBytes {
+        bytes,
+        truncated: false,
+    })
+}
+
+/// An asset whose body is read lazily.
+pub struct AssetDownload {
+    pub content_type: String,
+    /// Size of `body`, when known up front (i.e. the content is passed through
+    /// unchanged and the server sent `Content-Length`).
+    pub content_length: Option<u64>,
+    pub body: ByteStream,
+}
+
+impl AssetDownload {
+    /// Read the whole body into memory.
+    pub async fn bytes(mut self) -> Result<Vec<u8>, ScanError> {
+        let mut out = Vec::new();
+        while let Some(chunk) = self.body.next().await {
+            out.extend(chunk?);
+        }
+        Ok(out)
+    }
+}
+
+/// A response body that enforces a size limit while it is read.
+struct LimitedBody {
+    response: reqwest::Response,
+    url: String,
+    downloaded: u64,
+    total: Option<u64>,
+    max_size: u64,
+    progress: Option<ProgressCallback>,
+}
+
+impl LimitedBody {
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, ScanError> {
+        let chunk = match self
+            .response
+            .chunk()
+            .await
+            .map_err(ScanError::from_request)?
+        {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        self.downloaded += chunk.len() as u64;
+        if self.downloaded > self.max_size {
+            return Err(ScanError::TooLarge {
+                limit: self.max_size,
+            });
+        }
+        if let Some(progress) = &self.progress {
+            progress(&DownloadProgress {
+                url: self.url.clone(),
+                downloaded: self.downloaded,
+                total: self.total,
+            });
+        }
+        Ok(Some(chunk.to_vec()))
+    }
+
+    async fn collect(mut self, mut out: Vec<u8>) -> Result<Vec<u8>, ScanError> {
+        while let Some(chunk) = self.next_chunk().await? {
+            out.extend(chunk);
+        }
+        Ok(out)
+    }
+
+    fn into_stream(self) -> ByteStream {
+        stream::unfold(Some(self), |body| async move {
+            let mut body = body?;
+            match body.next_chunk().await {
+                Ok(Some(chunk)) => Some((Ok(chunk), Some(body))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Start downloading an asset, rejecting it early when the egress policy
+/// forbids the URL or `Content-Length` already exceeds `max_size`.
+///
+/// SVGs (which are always sanitized) and rasters that need resizing are
+/// buffered, up to `max_size`, so they can be processed. Everything else is
+/// streamed through unchanged.
+pub(crate) async fn open_asset(
+    cfg: &Config,
+    request: reqwest::RequestBuilder,
+    url: &str,
+    options: &RenderOptions,
+    max_size: u64,
+    progress: Option<ProgressCallback>,
+) -> Result<AssetDownload, ScanError> {
+    check_url_str(cfg, url).await?;
+    let response = retry::send(cfg, request)
+        .await
+        .0
+        .map_err(ScanError::from_request)?;
+
+    if !response.status().is_success() {
+        return Err(ScanError::upstream_status(url, response.status()));
+    }
+
+    let total = response.content_length();
+    if let Some(len) = total
+        && len > max_size
+    {
+        return Err(ScanError::TooLarge { limit: max_size });
+    }
+
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    let mut body = LimitedBody {
+        response,
+        url: url.to_string(),
+        downloaded: 0,
+        total,
+        max_size,
+        progress,
+    };
+
+    let first = body.next_chunk().await?.unwrap_or_default();
+    let sniffed = sniff_image_type(&first);
+    let needs_processing = content_type.contains("svg")
+        || sniffed == Some("image/svg+xml")
+        || (options.size.is_some() && sniffed.is_some());
+
+    if needs_processing {
+        let bytes = body.collect(first).await?;
+        let (bytes, content_type) =
+            prepare_image(bytes, content_type, options).map_err(ScanError::Parse)?;
+        return Ok(AssetDownload {
+            content_type,
+            content_length: Some(bytes.len() as u64),
+            body: stream::once(async move { Ok(bytes) }).boxed(),
+        });
+    }
+
+    Ok(AssetDownload {
+        content_type,
+        content_length: total,
+        body: stream::once(async move { Ok(first) })
+            .chain(body.into_stream())
+            .boxed(),
+    })
+}
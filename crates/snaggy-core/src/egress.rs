@@ -0,0 +1,404 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use url::Url;
+
+use crate::config::Config;
+use crate::credentials;
+use crate::error::ScanError;
+use crate::transport::{is_proxy_host, may_use_proxy};
+
+/// Why a request was refused by the egress policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EgressDenial {
+    /// The URL scheme is not in `allowed_schemes`.
+    Scheme(String),
+    /// The host matches `denied_hosts`.
+    DeniedHost(String),
+    /// The address is loopback, private, link-local or otherwise reserved.
+    PrivateAddress(IpAddr),
+    /// The URL has no host to connect to.
+    MissingHost,
+    /// The host, about to be sent to a proxy, did not resolve locally.
+    Unresolved(String),
+}
+
+/// A request blocked by the egress policy.
+#[derive(Debug, Clone)]
+pub struct EgressError {
+    /// The URL (or, at DNS time, the host name) that was blocked.
+    pub target: String,
+    pub denial: EgressDenial,
+}
+
+impl fmt::Display for EgressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Blocked by egress policy: {} (", self.target)?;
+        match &self.denial {
+            EgressDenial::Scheme(s) => write!(f, "scheme \"{}\" is not allowed", s)?,
+            EgressDenial::DeniedHost(h) => write!(f, "host {} is denied", h)?,
+            EgressDenial::PrivateAddress(ip) => {
+                write!(f, "{} is a private or reserved address", ip)?
+            }
+            EgressDenial::MissingHost => write!(f, "no host")?,
+            EgressDenial::Unresolved(h) => {
+                write!(f, "{} could not be resolved to check its address", h)?
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+impl Error for EgressError {}
+
+/// A host name that could not be resolved.
+#[derive(Debug)]
+pub(crate) struct LookupError {
+    host: String,
+    source: io::Error,
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to resolve {}: {}", self.host, self.source)
+    }
+}
+
+impl Error for LookupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // Carrier-grade NAT 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19))
+        // Reserved 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    // IPv4-mapped (::ffff:a.b.c.d) and NAT64 (64:ff9b::/96) addresses reach
+    // the embedded IPv4 host.
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_public_v4(v4);
+    }
+    let segments = ip.segments();
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [a, b] = segments[6].to_be_bytes();
+        let [c, d] = segments[7].to_be_bytes();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local fc00::/7
+        || (segments[0] & 0xfe00) == 0xfc00
+        // Link-local fe80::/10 and deprecated site-local fec0::/10
+        || (segments[0] & 0xffc0) == 0xfe80
+        || (segments[0] & 0xffc0) == 0xfec0
+        // Documentation 2001:db8::/32
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+        // Deprecated IPv4-compatible ::a.b.c.d
+        || segments[..6] == [0, 0, 0, 0, 0, 0])
+}
+
+/// True if the address is routable on the public internet.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_v4(v4),
+        IpAddr::V6(v6) => is_public_v6(v6),
+    }
+}
+
+/// Match a host against a pattern list. `*.example.com` matches subdomains
+/// of example.com; anything else must match exactly (case-insensitively).
+pub(crate) fn host_matches(host: &str, patterns: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim().to_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(suffix) => host.ends_with(&format!(".{}", suffix)),
+            None => host == pattern,
+        }
+    })
+}
+
+/// Check a resolved (or literal) address for a host.
+fn check_address(cfg: &Config, target: &str, host: &str, ip: IpAddr) -> Result<(), EgressError> {
+    if cfg.allow_private_networks || host_matches(host, &cfg.allowed_hosts) || is_public_ip(ip) {
+        Ok(())
+    } else {
+        Err(EgressError {
+            target: target.to_string(),
+            denial: EgressDenial::PrivateAddress(ip),
+        })
+    }
+}
+
+/// Check a URL's scheme and host before connecting. Host names are checked
+/// against the deny list here; their addresses are checked at DNS time.
+pub fn check_url(cfg: &Config, url: &Url) -> Result<(), EgressError> {
+    let deny = |denial| {
+        Err(EgressError {
+            target: url.to_string(),
+            denial,
+        })
+    };
+
+    if !cfg
+        .allowed_schemes
+        .iter()
+        .any(|s| s.eq_ignore_ascii_case(url.scheme()))
+    {
+        return deny(EgressDenial::Scheme(url.scheme().to_string()));
+    }
+
+    let host = match url.host() {
+        Some(h) => h,
+        None => return deny(EgressDenial::MissingHost),
+    };
+    let host_str = match &host {
+        url::Host::Domain(d) => d.to_string(),
+        url::Host::Ipv4(ip) => ip.to_string(),
+        url::Host::Ipv6(ip) => ip.to_string(),
+    };
+    if host_matches(&host_str, &cfg.denied_hosts) {
+        return deny(EgressDenial::DeniedHost(host_str));
+    }
+
+    // IP literals never reach the resolver, so check them here.
+    match host {
+        url::Host::Ipv4(ip) => check_address(cfg, url.as_str(), &host_str, IpAddr::V4(ip)),
+        url::Host::Ipv6(ip) => check_address(cfg, url.as_str(), &host_str, IpAddr::V6(ip)),
+        url::Host::Domain(_) => Ok(()),
+    }
+}
+
+/// [`check_url`], plus the addresses of a host that a proxy will connect
+/// to. The proxy does its own lookup, so [`PolicyResolver`] never sees the
+/// target. Redirect hops are not re-resolved, and the proxy may get a
+/// different answer, so its own egress rules remain the real boundary.
+pub(crate) async fn check_target(cfg: &Config, url: &Url) -> Result<(), EgressError> {
+    check_url(cfg, url)?;
+    match url.host() {
+        Some(url::Host::Domain(host)) if may_use_proxy(cfg, url) => {
+            check_proxied_host(cfg, url, host).await
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn check_proxied_host(cfg: &Config, url: &Url, host: &str) -> Result<(), EgressError> {
+    if cfg.allow_private_networks || host_matches(host, &cfg.allowed_hosts) {
+        return Ok(());
+    }
+    let port = url.port_or_known_default().unwrap_or(0);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map(|addrs| addrs.collect())
+        .unwrap_or_default();
+    if addrs.is_empty() {
+        return Err(EgressError {
+            target: url.to_string(),
+            denial: EgressDenial::Unresolved(host.to_string()),
+        });
+    }
+    addrs
+        .iter()
+        .try_for_each(|addr| check_address(cfg, url.as_str(), host, addr.ip()))
+}
+
+/// Parse and check a URL string with [`check_target`].
+pub(crate) async fn check_url_str(cfg: &Config, url: &str) -> Result<(), ScanError> {
+    let parsed = Url::parse(url).map_err(|e| ScanError::InvalidUrl(e.to_string()))?;
+    Ok(check_target(cfg, &parsed).await?)
+}
+
+/// Find an egress error in a reqwest error's source chain.
+pub fn egress_error(err: &reqwest::Error) -> Option<&EgressError> {
+    let mut source = err.source();
+    while let Some(e) = source {
+        if let Some(egress) = e.downcast_ref::<EgressError>() {
+            return Some(egress);
+        }
+        source = e.source();
+    }
+    None
+}
+
+/// DNS resolver that drops addresses the policy forbids, so a public host
+/// name can't be pointed at an internal address.
+pub(crate) struct PolicyResolver {
+    pub config: Arc<Config>,
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let cfg = self.config.clone();
+        Box::pin(async move {
+            if host_matches(&host, &cfg.denied_hosts) {
+                return Err(EgressError {
+                    target: host.clone(),
+                    denial: EgressDenial::DeniedHost(host),
+                }
+                .into());
+            }
+
+            let resolved: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await
+                .map_err(|source| LookupError {
+                    host: host.clone(),
+                    source,
+                })?
+                .collect();
+            if is_proxy_host(&cfg, &host) {
+                return Ok(Box::new(resolved.into_iter()) as Addrs);
+            }
+            let mut blocked = None;
+            let allowed: Vec<SocketAddr> = resolved
+                .into_iter()
+                .filter(|addr| match check_address(&cfg, &host, &host, addr.ip()) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        blocked.get_or_insert(e);
+                        false
+                    }
+                })
+                .collect();
+
+            match (allowed.is_empty(), blocked) {
+                (true, Some(e)) => Err(e.into()),
+                _ => Ok(Box::new(allowed.into_iter()) as Addrs),
+            }
+        })
+    }
+}
+
+/// Redirect policy that enforces `max_redirects` and checks every hop.
+/// Redirects that would carry custom headers to another host are refused.
+pub(crate) fn redirect_policy(cfg: Arc<Config>) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= cfg.max_redirects {
+            return attempt.error("too many redirects");
+        }
+        if let Some(first) = attempt.previous().first()
+            && credentials::leaks_headers(&cfg, first, attempt.url())
+        {
+            let message = format!(
+                "redirect to {} would send headers configured for {}",
+                attempt.url(),
+                first.host_str().unwrap_or_default()
+            );
+            return attempt.error(message);
+        }
+        match check_url(&cfg, attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(e) => attempt.error(e),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxied(configure: impl FnOnce(&mut Config)) -> Config {
+        let mut cfg = Config::default();
+        configure(&mut cfg);
+        cfg
+    }
+
+    fn check(cfg: &Config, url: &str) -> Result<(), EgressDenial> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime
+            .block_on(check_target(cfg, &Url::parse(url).unwrap()))
+            .map_err(|e| e.denial)
+    }
+
+    #[test]
+    fn loopback_host_is_blocked_behind_a_proxy() {
+        let cfg = proxied(|c| c.proxy = Some("http://proxy.example:3128".to_string()));
+        assert!(matches!(
+            check(&cfg, "http://localhost/"),
+            Err(EgressDenial::PrivateAddress(ip)) if ip.is_loopback()
+        ));
+        assert!(matches!(
+            check(&cfg, "https://localhost:8443/"),
+            Err(EgressDenial::PrivateAddress(_))
+        ));
+    }
+
+    #[test]
+    fn proxied_hosts_respect_the_allow_settings() {
+        let cfg = proxied(|c| {
+            c.proxy = Some("http://proxy.example:3128".to_string());
+            c.allowed_hosts = vec!["localhost".to_string()];
+        });
+        assert_eq!(check(&cfg, "http://localhost/"), Ok(()));
+
+        let cfg = proxied(|c| {
+            c.proxy = Some("http://proxy.example:3128".to_string());
+            c.allow_private_networks = true;
+        });
+        assert_eq!(check(&cfg, "http://localhost/"), Ok(()));
+    }
+
+    #[test]
+    fn scheme_proxy_only_resolves_its_scheme() {
+        // Plain http goes direct here, so the resolver checks it instead.
+        let cfg = proxied(|c| c.https_proxy = Some("http://proxy.example:3128".to_string()));
+        assert_eq!(check(&cfg, "http://localhost/"), Ok(()));
+        assert!(matches!(
+            check(&cfg, "https://localhost/"),
+            Err(EgressDenial::PrivateAddress(_))
+        ));
+    }
+
+    #[test]
+    fn unresolvable_proxied_host_is_blocked() {
+        let cfg = proxied(|c| c.proxy = Some("http://proxy.example:3128".to_string()));
+        assert_eq!(
+            check(&cfg, "http://does-not-exist.invalid/"),
+            Err(EgressDenial::Unresolved(
+                "does-not-exist.invalid".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn ip_literals_are_checked() {
+        let cfg = Config::default();
+        assert!(matches!(
+            check(&cfg, "http://127.0.0.1/"),
+            Err(EgressDenial::PrivateAddress(_))
+        ));
+        assert!(matches!(
+            check(&cfg, "http://[::ffff:10.0.0.1]/"),
+            Err(EgressDenial::PrivateAddress(_))
+        ));
+        assert_eq!(check(&cfg, "http://93.184.216.34/"), Ok(()));
+    }
+}
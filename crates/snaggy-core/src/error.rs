@@ -0,0 +1,299 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use serde::{Serialize, Serializer};
+
+use crate::config::ConfigError;
+use crate::egress::{EgressError, LookupError, egress_error};
+
+/// I/O errors from a connection that was refused, reset or closed early.
+const DROPPED_IO: [io::ErrorKind; 7] = [
+    io::ErrorKind::ConnectionRefused,
+    io::ErrorKind::ConnectionReset,
+    io::ErrorKind::ConnectionAborted,
+    io::ErrorKind::NotConnected,
+    io::ErrorKind::BrokenPipe,
+    io::ErrorKind::UnexpectedEof,
+    io::ErrorKind::Interrupted,
+];
+
+/// What a failed request's error chain points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Cause {
+    Timeout,
+    Dns,
+    Tls,
+    /// The connection was refused, reset or closed mid-request.
+    Dropped,
+    Other,
+}
+
+impl Cause {
+    /// Classify a request error by the types in its source chain.
+    pub(crate) fn of(err: &reqwest::Error) -> Self {
+        if err.is_timeout() {
+            return Cause::Timeout;
+        }
+        let mut kind = None;
+        let mut source = err.source();
+        while let Some(e) = source {
+            if e.is::<LookupError>() {
+                return Cause::Dns;
+            }
+            if e.is::<rustls::Error>() {
+                return Cause::Tls;
+            }
+            if let Some(hyper) = e.downcast_ref::<hyper::Error>()
+                && (hyper.is_incomplete_message() || hyper.is_closed() || hyper.is_canceled())
+            {
+                return Cause::Dropped;
+            }
+            if let Some(io) = e.downcast_ref::<io::Error>() {
+                kind = Some(io.kind());
+                // `source()` skips the error an I/O error wraps, which is
+                // where TLS handshake failures end up.
+                if let Some(inner) = io.get_ref() {
+                    source = Some(inner);
+                    continue;
+                }
+            }
+            source = e.source();
+        }
+        // The innermost I/O error is the most specific.
+        match kind {
+            Some(io::ErrorKind::TimedOut) => Cause::Timeout,
+            Some(kind) if DROPPED_IO.contains(&kind) => Cause::Dropped,
+            _ => Cause::Other,
+        }
+    }
+}
+
+/// Why a scan, download or export failed.
+///
+/// Serializes as `{ "code", "message" }`, plus `status` for upstream HTTP
+/// errors and `limit` for oversized responses, so clients can branch on
+/// `code` instead of parsing the message.
+#[derive(Debug, Clone)]
+pub enum ScanError {
+    /// The URL could not be parsed.
+    InvalidUrl(String),
+    /// The request itself is unusable (e.g. no assets, or too many).
+    InvalidRequest(String),
+    /// Nothing usable was found (e.g. no reachable icon).
+    NotFound(String),
+    /// The egress policy refused the URL or one of its redirects.
+    Blocked(EgressError),
+    /// The host name could not be resolved.
+    Dns(String),
+    Timeout(String),
+    /// Certificate or handshake failure.
+    Tls(String),
+    /// Connection refused or reset, too many redirects, or a broken body.
+    Connection(String),
+    /// The upstream server answered with a non-success status.
+    UpstreamStatus {
+        url: String,
+        status: u16,
+    },
+    /// The response is larger than the configured limit.
+    TooLarge {
+        limit: u64,
+    },
+    /// The content could not be decoded, parsed or rendered.
+    Parse(String),
+    /// Reading or writing a local file failed.
+    Io(String),
+    /// A configuration value is missing or invalid.
+    Config(String),
+    Internal(String),
+}
+
+impl ScanError {
+    /// Stable machine-readable code, e.g. `"dns"` or `"upstream-status"`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ScanError::InvalidUrl(_) => "invalid-url",
+            ScanError::InvalidRequest(_) => "invalid-request",
+            ScanError::NotFound(_) => "not-found",
+            ScanError::Blocked(_) => "blocked",
+            ScanError::Dns(_) => "dns",
+            ScanError::Timeout(_) => "timeout",
+            ScanError::Tls(_) => "tls",
+            ScanError::Connection(_) => "connection",
+            ScanError::UpstreamStatus { .. } => "upstream-status",
+            ScanError::TooLarge { .. } => "too-large",
+            ScanError::Parse(_) => "parse",
+            ScanError::Io(_) => "io",
+            ScanError::Config(_) => "config",
+            ScanError::Internal(_) => "internal",
+        }
+    }
+
+    /// Classify a failed request by walking its error chain.
+    pub(crate) fn from_request(err: reqwest::Error) -> Self {
+        if let Some(egress) = egress_error(&err) {
+            return ScanError::Blocked(egress.clone());
+        }
+
+        // reqwest's own message only names the URL; the cause is at the end
+        // of the chain.
+        let mut chain = Vec::new();
+        let mut source = err.source();
+        while let Some(e) = source {
+            chain.push(e.to_string());
+            source = e.source();
+        }
+        let message = match chain.last() {
+            Some(cause) => format!("{}: {}", err, cause),
+            None => err.to_string(),
+        };
+
+        match Cause::of(&err) {
+            Cause::Timeout => ScanError::Timeout(message),
+            Cause::Dns => ScanError::Dns(message),
+            Cause::Tls => ScanError::Tls(message),
+            Cause::Dropped | Cause::Other => ScanError::Connection(message),
+        }
+    }
+
+    pub(crate) fn upstream_status(url: impl Into<String>, status: reqwest::StatusCode) -> Self {
+        ScanError::UpstreamStatus {
+            url: url.into(),
+            status: status.as_u16(),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::InvalidUrl(m) => write!(f, "Invalid URL: {}", m),
+            ScanError::Blocked(e) => write!(f, "{}", e),
+            ScanError::Dns(m) => write!(f, "DNS lookup failed: {}", m),
+            ScanError::Timeout(m) => write!(f, "Request timed out: {}", m),
+            ScanError::Tls(m) => write!(f, "TLS error: {}", m),
+            ScanError::Connection(m) => write!(f, "Connection failed: {}", m),
+            ScanError::UpstreamStatus { url, status } => {
+                write!(f, "{} returned status {}", url, status)
+            }
+            ScanError::Config(m) => write!(f, "Invalid configuration: {}", m),
+            ScanError::TooLarge { limit } => {
+                write!(f, "Response exceeds the maximum size of {} bytes", limit)
+            }
+            ScanError::InvalidRequest(m)
+            | ScanError::NotFound(m)
+            | ScanError::Parse(m)
+            | ScanError::Io(m)
+            | ScanError::Internal(m) => write!(f, "{}", m),
+        }
+    }
+}
+
+impl Error for ScanError {}
+
+impl From<EgressError> for ScanError {
+    fn from(err: EgressError) -> Self {
+        ScanError::Blocked(err)
+    }
+}
+
+impl From<ConfigError> for ScanError {
+    fn from(err: ConfigError) -> Self {
+        ScanError::Config(err.to_string())
+    }
+}
+
+#[derive(Serialize)]
+struct ScanErrorBody {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u64>,
+}
+
+impl Serialize for ScanError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ScanErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+            status: match self {
+                ScanError::UpstreamStatus { status, .. } => Some(*status),
+                _ => None,
+            },
+            limit: match self {
+                ScanError::TooLarge { limit } => Some(*limit),
+                _ => None,
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::scanner::Scanner;
+    use tokio::io::AsyncWriteExt;
+
+    async fn cause_of(url: &str) -> Cause {
+        let scanner = Scanner::new(Config {
+            allow_private_networks: true,
+            ..Config::default()
+        })
+        .unwrap();
+        let err = scanner.client().get(url).send().await.unwrap_err();
+        Cause::of(&err)
+    }
+
+    #[test]
+    fn classifies_request_failures_by_type() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // Nothing listens on a port we bound and released.
+            let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = closed.local_addr().unwrap().port();
+            drop(closed);
+            assert_eq!(
+                cause_of(&format!("http://127.0.0.1:{}/", port)).await,
+                Cause::Dropped
+            );
+
+            assert_eq!(cause_of("http://does-not-exist.invalid/").await, Cause::Dns);
+
+            // A plain-text answer to a TLS handshake.
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\n\r\n")
+                        .await;
+                }
+            });
+            assert_eq!(
+                cause_of(&format!("https://127.0.0.1:{}/", port)).await,
+                Cause::Tls
+            );
+
+            // A connection closed before any response.
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    drop(socket);
+                }
+            });
+            assert_eq!(
+                cause_of(&format!("http://127.0.0.1:{}/", port)).await,
+                Cause::Dropped
+            );
+        });
+    }
+}
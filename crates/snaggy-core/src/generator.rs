@@ -0,0 +1,358 @@
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops;
+use image::{ExtendedColorType, Rgba, RgbaImage};
+use resvg::usvg;
+use serde::{Deserialize, Serialize};
+
+use crate::archive::{ArchiveEntry, build_zip};
+use crate::error::ScanError;
+use crate::probe::sniff_image_type;
+use crate::scanner::Scanner;
+use crate::svg::{RenderOptions, encode_png, fit_square, render_svg, svg_options};
+
+/// Sizes bundled into the generated `favicon.ico`.
+const ICO_SIZES: &[u32] = &[16, 32, 48];
+
+/// Standalone PNGs written alongside the ICO: (file name, size).
+const PNG_SIZES: &[(&str, u32)] = &[
+    ("favicon-16x16.png", 16),
+    ("favicon-32x32.png", 32),
+    ("favicon-48x48.png", 48),
+    ("android-chrome-192x192.png", 192),
+    ("android-chrome-512x512.png", 512),
+];
+
+const APPLE_TOUCH_SIZE: u32 = 180;
+
+/// Options for the generated manifest and HTML snippet.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FaviconSetOptions {
+    pub name: Option<String>,
+    pub short_name: Option<String>,
+    pub theme_color: Option<String>,
+    /// Also used to fill the apple-touch-icon, which iOS renders black where
+    /// transparent. Defaults to white.
+    pub background_color: Option<String>,
+}
+
+/// A generated favicon package.
+#[derive(Debug, Clone)]
+pub struct FaviconSet {
+    pub files: Vec<ArchiveEntry>,
+    /// `<link>`/`<meta>` tags to paste into the page `<head>`.
+    pub html: String,
+}
+
+impl FaviconSet {
+    /// Package every file (plus the HTML snippet) as a ZIP archive.
+    pub fn to_zip(&self) -> Result<Vec<u8>, ScanError> {
+        let mut entries = self.files.clone();
+        entries.push(ArchiveEntry::new("favicon.html", self.html.as_bytes()));
+        build_zip(&entries).map_err(ScanError::Internal)
+    }
+}
+
+enum SourceImage {
+    Raster(RgbaImage),
+    Svg(Box<usvg::Tree>),
+}
+
+fn render(source: &SourceImage, size: u32) -> Result<RgbaImage, String> {
+    match source {
+        SourceImage::Raster(image) => Ok(fit_square(image, size)),
+        SourceImage::Svg(tree) => render_svg(tree, size),
+    }
+}
+
+fn load_source(bytes: &[u8]) -> Result<SourceImage, String> {
+    match sniff_image_type(bytes) {
+        Some("image/svg+xml") => usvg::Tree::from_data(bytes, &svg_options())
+            .map(|t| SourceImage::Svg(Box::new(t)))
+            .map_err(|e| format!("Failed to parse SVG: {}", e)),
+        // For ICO sources the decoder picks the largest embedded frame.
+        Some(_) => image::load_from_memory(bytes)
+            .map(|i| SourceImage::Raster(i.to_rgba8()))
+            .map_err(|e| format!("Failed to decode image: {}", e)),
+        None => Err("Unsupported source image format".to_string()),
+    }
+}
+
+/// Parse `#rgb` / `#rrggbb` into an opaque color.
+fn parse_hex_color(value: &str) -> Option<Rgba<u8>> {
+    let hex = value.trim().strip_prefix('#')?;
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return None,
+    };
+    let n = u32::from_str_radix(&expanded, 16).ok()?;
+    Some(Rgba([(n >> 16) as u8, (n >> 8) as u8, n as u8, 255]))
+}
+
+fn encode_ico(source: &SourceImage) -> Result<Vec<u8>, String> {
+    let mut frames = Vec::new();
+    for &size in ICO_SIZES {
+        let image = render(source, size)?;
+        let frame = IcoFrame::as_png(image.as_raw(), size, size, ExtendedColorType::Rgba8)
+            .map_err(|e| format!("Failed to encode ICO frame: {}", e))?;
+        frames.push(frame);
+    }
+
+    let mut buf = Vec::new();
+    IcoEncoder::new(&mut buf)
+        .encode_images(&frames)
+        .map_err(|e| format!("Failed to encode ICO: {}", e))?;
+    Ok(buf)
+}
+
+fn webmanifest(options: &FaviconSetOptions) -> String {
+    let icons: Vec<serde_json::Value> = PNG_SIZES
+        .iter()
+        .filter(|(name, _)| name.starts_with("android-chrome"))
+        .map(|(name, size)| {
+            serde_json::json!({
+                "src": format!("/{}", name),
+                "sizes": format!("{}x{}", size, size),
+                "type": "image/png",
+            })
+        })
+        .collect();
+
+    let manifest = serde_json::json!({
+        "name": options.name.clone().unwrap_or_default(),
+        "short_name": options.short_name.clone().or_else(|| options.name.clone()).unwrap_or_default(),
+        "icons": icons,
+        "theme_color": options.theme_color.clone().unwrap_or_else(|| "#ffffff".to_string()),
+        "background_color": options.background_color.clone().unwrap_or_else(|| "#ffffff".to_string()),
+        "display": "standalone",
+    });
+    serde_json::to_string_pretty(&manifest).unwrap_or_default()
+}
+
+fn html_snippet(options: &FaviconSetOptions) -> String {
+    let mut lines = vec![
+        r#"<link rel="icon" href="/favicon.ico" sizes="16x16 32x32 48x48">"#.to_string(),
+        r#"<link rel="icon" type="image/png" sizes="32x32" href="/favicon-32x32.png">"#.to_string(),
+        r#"<link rel="icon" type="image/png" sizes="16x16" href="/favicon-16x16.png">"#.to_string(),
+        r#"<link rel="apple-touch-icon" sizes="180x180" href="/apple-touch-icon.png">"#.to_string(),
+        r#"<link rel="manifest" href="/site.webmanifest">"#.to_string(),
+    ];
+    if let Some(color) = &options.theme_color {
+        lines.push(format!(
+            r#"<meta name="theme-color" content="{}">"#,
+            color.replace('"', "&quot;")
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Generate a complete favicon package from a PNG, SVG, ICO (largest frame)
+/// or other supported raster image.
+pub fn generate_favicon_set(
+    source: &[u8],
+    options: &FaviconSetOptions,
+) -> Result<FaviconSet, String> {
+    let source = load_source(source)?;
+    let mut files = vec![ArchiveEntry::new("favicon.ico", encode_ico(&source)?)];
+
+    for &(name, size) in PNG_SIZES {
+        files.push(ArchiveEntry::new(
+            name,
+            encode_png(&render(&source, size)?)?,
+        ));
+    }
+
+    let background = options
+        .background_color
+        .as_deref()
+        .and_then(parse_hex_color)
+        .unwrap_or(Rgba([255, 255, 255, 255]));
+    let icon = render(&source, APPLE_TOUCH_SIZE)?;
+    let mut apple = RgbaImage::from_pixel(APPLE_TOUCH_SIZE, APPLE_TOUCH_SIZE, background);
+    imageops::overlay(&mut apple, &icon, 0, 0);
+    files.push(ArchiveEntry::new(
+        "apple-touch-icon.png",
+        encode_png(&apple)?,
+    ));
+
+    files.push(ArchiveEntry::new("site.webmanifest", webmanifest(options)));
+
+    Ok(FaviconSet {
+        files,
+        html: html_snippet(options),
+    })
+}
+
+/// Download a source icon and generate a favicon package from it.
+pub async fn generate_favicon_set_from_url(
+    scanner: &Scanner,
+    url: &str,
+    options: &FaviconSetOptions,
+) -> Result<FaviconSet, ScanError> {
+    let (bytes, _) = scanner
+        .fetch_asset_bytes(
+            url,
+            &RenderOptions::default(),
+            scanner.config().max_image_size,
+        )
+        .await?;
+    let options = options.clone();
+
+    tokio::task::spawn_blocking(move || generate_favicon_set(&bytes, &options))
+        .await
+        .map_err(|e| ScanError::Internal(format!("Generator task failed: {}", e)))?
+        .map_err(ScanError::Parse)
+}
+
+/// Generate a favicon package from a URL and save it as a ZIP file.
+pub async fn save_favicon_set(
+    scanner: &Scanner,
+    url: &str,
+    options: &FaviconSetOptions,
+    save_path: &str,
+) -> Result<(), ScanError> {
+    let zip = generate_favicon_set_from_url(scanner, url, options)
+        .await?
+        .to_zip()?;
+
+    tokio::fs::write(save_path, &zip)
+        .await
+        .map_err(|e| ScanError::Io(format!("Failed to write file: {}", e)))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use super::*;
+    use crate::decode::decode_icon;
+
+    fn source_png() -> Vec<u8> {
+        // Opaque red on the left half, transparent on the right.
+        let image = RgbaImage::from_fn(64, 32, |x, _| {
+            if x < 32 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        encode_png(&image).unwrap()
+    }
+
+    fn file<'a>(set: &'a FaviconSet, name: &str) -> &'a [u8] {
+        &set.files.iter().find(|f| f.name == name).unwrap().bytes
+    }
+
+    #[test]
+    fn ico_holds_16_32_and_48_pixel_frames() {
+        let set = generate_favicon_set(&source_png(), &FaviconSetOptions::default()).unwrap();
+        let details = decode_icon(file(&set, "favicon.ico"), None).unwrap();
+        assert_eq!(details.mime_type, "image/x-icon");
+        let sizes: Vec<(u32, u32, &str)> = details
+            .frames
+            .iter()
+            .map(|f| (f.width, f.height, f.format.as_str()))
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![(16, 16, "png"), (32, 32, "png"), (48, 48, "png")]
+        );
+    }
+
+    #[test]
+    fn pngs_are_square_at_their_named_sizes() {
+        let set = generate_favicon_set(&source_png(), &FaviconSetOptions::default()).unwrap();
+        for &(name, size) in PNG_SIZES.iter().chain(&[("apple-touch-icon.png", 180)]) {
+            let image = image::load_from_memory(file(&set, name)).unwrap();
+            assert_eq!((image.width(), image.height()), (size, size), "{}", name);
+        }
+    }
+
+    #[test]
+    fn apple_touch_icon_is_filled_with_the_background() {
+        let options = FaviconSetOptions {
+            background_color: Some("#0f0".to_string()),
+            ..FaviconSetOptions::default()
+        };
+        let set = generate_favicon_set(&source_png(), &options).unwrap();
+        let image = image::load_from_memory(file(&set, "apple-touch-icon.png"))
+            .unwrap()
+            .to_rgba8();
+        // The source is letterboxed, so the corners show the background.
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 255, 0, 255]));
+        assert_eq!(image.get_pixel(179, 179), &Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn webmanifest_lists_the_android_icons() {
+        let options = FaviconSetOptions {
+            name: Some("Example".to_string()),
+            theme_color: Some("#123456".to_string()),
+            ..FaviconSetOptions::default()
+        };
+        let set = generate_favicon_set(&source_png(), &options).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_slice(file(&set, "site.webmanifest")).unwrap();
+        assert_eq!(
+            manifest,
+            serde_json::json!({
+                "name": "Example",
+                "short_name": "Example",
+                "icons": [
+                    {"src": "/android-chrome-192x192.png", "sizes": "192x192", "type": "image/png"},
+                    {"src": "/android-chrome-512x512.png", "sizes": "512x512", "type": "image/png"},
+                ],
+                "theme_color": "#123456",
+                "background_color": "#ffffff",
+                "display": "standalone",
+            })
+        );
+        assert!(
+            set.html
+                .contains(r##"<meta name="theme-color" content="#123456">"##)
+        );
+    }
+
+    #[test]
+    fn zip_contains_every_file_and_the_snippet() {
+        let set = generate_favicon_set(&source_png(), &FaviconSetOptions::default()).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(set.to_zip().unwrap())).unwrap();
+        let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+        let mut expected = vec![
+            "favicon.ico",
+            "favicon-16x16.png",
+            "favicon-32x32.png",
+            "favicon-48x48.png",
+            "android-chrome-192x192.png",
+            "android-chrome-512x512.png",
+            "apple-touch-icon.png",
+            "site.webmanifest",
+            "favicon.html",
+        ];
+        let mut names_sorted = names.clone();
+        names_sorted.sort();
+        expected.sort();
+        assert_eq!(names_sorted, expected);
+
+        let mut html = String::new();
+        archive
+            .by_name("favicon.html")
+            .unwrap()
+            .read_to_string(&mut html)
+            .unwrap();
+        assert_eq!(html, set.html);
+    }
+
+    #[test]
+    fn svg_sources_are_rendered() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><rect width="10" height="10" fill="blue"/></svg>"#;
+        let set = generate_favicon_set(svg, &FaviconSetOptions::default()).unwrap();
+        let image = image::load_from_memory(file(&set, "favicon-32x32.png"))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(image.get_pixel(16, 16), &Rgba([0, 0, 255, 255]));
+        assert!(generate_favicon_set(b"not an image", &FaviconSetOptions::default()).is_err());
+    }
+}
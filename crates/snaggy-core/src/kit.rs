@@ -0,0 +1,309 @@
+use futures::stream::{self, StreamExt};
+
+use crate::archive::{ArchiveEntry, build_zip, write_entries};
+use crate::error::ScanError;
+use crate::scanner::{FontInfo, FontVariant, Scanner};
+use crate::svg::RenderOptions;
+use crate::tokens::css_string;
+
+/// Folder (inside the kit) that holds the font files.
+const FONTS_DIR: &str = "fonts";
+
+/// A self-hosting package: font files plus a stylesheet referencing them.
+#[derive(Debug, Clone)]
+pub struct FontKit {
+    pub files: Vec<ArchiveEntry>,
+    /// `@font-face` rules pointing at the files under `fonts/`.
+    pub css: String,
+}
+
+impl FontKit {
+    fn entries(&self) -> Vec<ArchiveEntry> {
+        let mut entries = self.files.clone();
+        entries.push(ArchiveEntry::new("fonts.css", self.css.as_bytes()));
+        entries
+    }
+
+    /// Package the font files and `fonts.css` as a ZIP archive.
+    pub fn to_zip(&self) -> Result<Vec<u8>, ScanError> {
+        build_zip(&self.entries()).map_err(ScanError::Internal)
+    }
+
+    /// Write the font files and `fonts.css` into a folder.
+    pub async fn write_to(&self, dir: &str) -> Result<(), ScanError> {
+        write_entries(&self.entries(), dir)
+            .await
+            .map_err(ScanError::Io)
+    }
+}
+
+fn slugify(value: &str) -> String {
+    let mut slug = String::new();
+    for ch in value.chars() {
+        if ch.is_ascii_alphanumeric() {
+            slug.push(ch.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+fn file_extension(variant: &FontVariant) -> String {
+    let ext = match variant.format.to_lowercase().as_str() {
+        "woff2" => "woff2",
+        "woff" => "woff",
+        "truetype" | "ttf" => "ttf",
+        "opentype" | "otf" => "otf",
+        "embedded-opentype" | "eot" => "eot",
+        "svg" => "svg",
+        _ => "",
+    };
+    if !ext.is_empty() {
+        return ext.to_string();
+    }
+    // Fall back to the URL's own extension.
+    variant
+        .url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_lowercase())
+        .filter(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or_else(|| "bin".to_string())
+}
+
+/// `inter-700-italic.woff2`, with a numeric suffix when several files share
+/// a weight and style (e.g. per-`unicode-range` subsets).
+fn file_name(family: &str, variant: &FontVariant, taken: &[String]) -> String {
+    let stem = format!(
+        "{}-{}-{}",
+        slugify(family),
+        slugify(&variant.weight),
+        slugify(&variant.style)
+    );
+    let ext = file_extension(variant);
+
+    let mut name = format!("{}.{}", stem, ext);
+    let mut n = 2;
+    while taken.contains(&name) {
+        name = format!("{}-{}.{}", stem, n, ext);
+        n += 1;
+    }
+    name
+}
+
+fn font_face_rule(family: &str, variant: &FontVariant, file: &str) -> String {
+    let format = match variant.format.as_str() {
+        "unknown" => String::new(),
+        f => format!(" format(\"{}\")", f),
+    };
+    let mut rule = format!(
+        "@font-face {{\n  font-family: {};\n  src: url(\"{}/{}\"){};\n",
+        css_string(family),
+        FONTS_DIR,
+        file,
+        format
+    );
+    // Weight and style come from the original rule when it declared them.
+    if !variant.descriptors.contains_key("font-weight") {
+        rule.push_str(&format!("  font-weight: {};\n", variant.weight));
+    }
+    if !variant.descriptors.contains_key("font-style") {
+        rule.push_str(&format!("  font-style: {};\n", variant.style));
+    }
+    for (name, value) in &variant.descriptors {
+        rule.push_str(&format!("  {}: {};\n", name, value));
+    }
+    rule.push_str("}\n");
+    rule
+}
+
+/// Download every variant of the selected families (all families when
+/// `families` is empty) and build a self-hosting kit.
+///
+/// Variants that fail to download are left out and noted in `fonts.css`;
+/// if none download, the first failure is returned.
+pub async fn build_font_kit(
+    scanner: &Scanner,
+    fonts: &[FontInfo],
+    families: &[String],
+) -> Result<FontKit, ScanError> {
+    let selected: Vec<&FontInfo> = fonts
+        .iter()
+        .filter(|f| {
+            families.is_empty() || families.iter().any(|s| s.eq_ignore_ascii_case(&f.family))
+        })
+        .collect();
+    if selected.is_empty() {
+        return Err(ScanError::NotFound(
+            "No matching font families found".to_string(),
+        ));
+    }
+
+    let mut jobs = Vec::new();
+    let mut taken = Vec::new();
+    for font in &selected {
+        for variant in &font.variants {
+            let name = file_name(&font.family, variant, &taken);
+            taken.push(name.clone());
+            jobs.push((font.family.clone(), variant.clone(), name));
+        }
+    }
+
+    let downloads: Vec<_> = stream::iter(jobs)
+        .map(|(family, variant, name)| async move {
+            let result = scanner
+                .fetch_asset_bytes(
+                    &variant.url,
+                    &RenderOptions::default(),
+                    scanner.config().max_font_size,
+                )
+                .await;
+            (family, variant, name, result)
+        })
+        .buffered(scanner.config().download_concurrency)
+        .collect()
+        .await;
+
+    let mut files = Vec::new();
+    let mut rules = Vec::new();
+    let mut skipped = Vec::new();
+    let mut first_error = None;
+    for (family, variant, name, result) in downloads {
+        match result {
+            Ok((bytes, _)) => {
+                rules.push(font_face_rule(&family, &variant, &name));
+                files.push(ArchiveEntry::new(format!("{}/{}", FONTS_DIR, name), bytes));
+            }
+            Err(e) => {
+                skipped.push(format!("{} ({})", variant.url, e));
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    if files.is_empty() {
+        return Err(first_error.unwrap_or_else(|| {
+            ScanError::NotFound("The selected families have no font files".to_string())
+        }));
+    }
+
+    let mut css = rules.join("\n");
+    if !skipped.is_empty() {
+        css.push_str("\n/* Skipped (download failed):\n");
+        for line in &skipped {
+            css.push_str(&format!("   {}\n", line.replace("*/", "* /")));
+        }
+        css.push_str("*/\n");
+    }
+
+    Ok(FontKit { files, css })
+}
+
+/// Scan a website and build a self-hosting kit for the selected families.
+pub async fn build_font_kit_from_url(
+    scanner: &Scanner,
+    url: &str,
+    families: &[String],
+) -> Result<FontKit, ScanError> {
+    let result = scanner.scan_website(url).await?;
+    build_font_kit(scanner, &result.fonts, families).await
+}
+
+/// Build a font kit from a URL and write it into a folder.
+pub async fn save_font_kit(
+    scanner: &Scanner,
+    url: &str,
+    families: &[String],
+    dir: &str,
+) -> Result<(), ScanError> {
+    build_font_kit_from_url(scanner, url, families)
+        .await?
+        .write_to(dir)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn variant(weight: &str, style: &str, url: &str, format: &str) -> FontVariant {
+        FontVariant {
+            style: style.to_string(),
+            weight: weight.to_string(),
+            url: url.to_string(),
+            format: format.to_string(),
+            descriptors: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn file_names_are_unique() {
+        let bold = variant("700", "italic", "https://cdn.example.com/a.woff2", "woff2");
+        let mut taken = Vec::new();
+        for _ in 0..3 {
+            let name = file_name("Inter Display", &bold, &taken);
+            taken.push(name);
+        }
+        assert_eq!(
+            taken,
+            vec![
+                "inter-display-700-italic.woff2",
+                "inter-display-700-italic-2.woff2",
+                "inter-display-700-italic-3.woff2",
+            ]
+        );
+
+        // Same stem, different extension: no clash.
+        let ttf = variant("700", "italic", "https://cdn.example.com/a.ttf", "truetype");
+        assert_eq!(
+            file_name("Inter Display", &ttf, &taken),
+            "inter-display-700-italic.ttf"
+        );
+    }
+
+    #[test]
+    fn extensions_fall_back_to_the_url() {
+        let from_url = variant("400", "normal", "/f/font.OTF?v=2#x", "unknown");
+        assert_eq!(file_extension(&from_url), "otf");
+        let opaque = variant("400", "normal", "/f/font?id=3", "unknown");
+        assert_eq!(file_extension(&opaque), "bin");
+    }
+
+    #[test]
+    fn font_face_rule_keeps_declared_descriptors() {
+        let mut declared = variant("100 900", "normal", "/inter.woff2", "woff2");
+        declared
+            .descriptors
+            .insert("font-weight".to_string(), "100 900".to_string());
+        declared
+            .descriptors
+            .insert("unicode-range".to_string(), "U+0000-00FF".to_string());
+        declared
+            .descriptors
+            .insert("font-display".to_string(), "swap".to_string());
+        assert_eq!(
+            font_face_rule("Inter", &declared, "inter-100-900-normal.woff2"),
+            "@font-face {\n  font-family: \"Inter\";\n  src: url(\"fonts/inter-100-900-normal.woff2\") format(\"woff2\");\n  font-style: normal;\n  font-display: swap;\n  font-weight: 100 900;\n  unicode-range: U+0000-00FF;\n}\n"
+        );
+    }
+
+    #[test]
+    fn font_face_rule_fills_in_weight_and_style() {
+        let plain = variant("700", "italic", "/x", "unknown");
+        assert_eq!(
+            font_face_rule(r#"Odd "Name\"#, &plain, "odd-name-700-italic.bin"),
+            r#"@font-face {
+  font-family: "Odd \"Name\\";
+  src: url("fonts/odd-name-700-italic.bin");
+  font-weight: 700;
+  font-style: italic;
+}
+"#
+        );
+    }
+}
@@ -1,2 +1,24 @@
+pub mod archive;
+pub mod browserconfig;
+pub mod bulk;
 pub mod config;
+pub mod credentials;
+pub mod decode;
+pub mod diagnostics;
+pub mod download;
+pub mod egress;
+pub mod error;
+pub mod generator;
+pub mod kit;
+pub mod logo;
+pub mod manifest;
+pub mod options;
+pub mod palette;
+pub mod probe;
+pub mod ranking;
+mod retry;
 pub mod scanner;
+pub mod social;
+pub mod svg;
+pub mod tokens;
+mod transport;
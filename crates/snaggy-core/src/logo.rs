@@ -0,0 +1,290 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::social::json_ld_logos;
+use crate::svg::sanitize_svg;
+
+/// Maximum number of candidates returned per page.
+const MAX_CANDIDATES: usize = 10;
+
+/// A possible site logo found on the page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogoCandidate {
+    /// Image URL; `None` for inline SVGs.
+    pub url: Option<String>,
+    /// Standalone, sanitized SVG markup for inline `<svg>` logos.
+    pub svg: Option<String>,
+    /// "img", "inline-svg" or "json-ld".
+    pub kind: String,
+    pub alt: Option<String>,
+    pub score: i32,
+    /// Human-readable reasons the candidate was selected.
+    pub evidence: Vec<String>,
+}
+
+fn mentions_logo(value: Option<&str>) -> bool {
+    value.is_some_and(|v| v.to_lowercase().contains("logo"))
+}
+
+/// True if the link points at the site's homepage.
+fn is_homepage_link(href: &str, base_url: &Url) -> bool {
+    match base_url.join(href.trim()) {
+        Ok(u) => {
+            u.host_str() == base_url.host_str()
+                && matches!(u.path(), "" | "/")
+                && u.query().is_none()
+        }
+        Err(_) => false,
+    }
+}
+
+/// Collect evidence from the element itself and its ancestors.
+///
+/// Page regions (`<header>`, `<nav>`, `<footer>`, banners) only adjust the
+/// score: an element with nothing else marking it as a logo scores 0, so
+/// ordinary navigation icons never become candidates.
+fn gather_evidence(element: &ElementRef, base_url: &Url) -> (i32, Vec<String>) {
+    let mut score = 0;
+    let mut identified = false;
+    let mut evidence = Vec::new();
+    let mut add = |points: i32, reason: &str, evidence: &mut Vec<String>| {
+        if !evidence.iter().any(|e| e == reason) {
+            score += points;
+            evidence.push(reason.to_string());
+        }
+    };
+
+    let v = element.value();
+    if mentions_logo(v.attr("class")) || mentions_logo(v.attr("id")) {
+        identified = true;
+        add(40, "\"logo\" in element class/id", &mut evidence);
+    }
+    if mentions_logo(v.attr("alt"))
+        || mentions_logo(v.attr("aria-label"))
+        || mentions_logo(v.attr("title"))
+    {
+        identified = true;
+        add(30, "alt/label mentions \"logo\"", &mut evidence);
+    }
+    if mentions_logo(v.attr("src")) {
+        identified = true;
+        add(20, "file name mentions \"logo\"", &mut evidence);
+    }
+
+    for ancestor in element.ancestors().filter_map(ElementRef::wrap) {
+        let a = ancestor.value();
+        match a.name() {
+            "header" => add(20, "inside <header>", &mut evidence),
+            "nav" => add(10, "inside <nav>", &mut evidence),
+            "footer" => add(-20, "inside <footer>", &mut evidence),
+            "a" if a
+                .attr("href")
+                .is_some_and(|h| is_homepage_link(h, base_url)) =>
+            {
+                identified = true;
+                add(35, "links to the homepage", &mut evidence);
+            }
+            _ => {}
+        }
+        if a.attr("role") == Some("banner") {
+            add(20, "inside role=\"banner\"", &mut evidence);
+        }
+        if mentions_logo(a.attr("class")) || mentions_logo(a.attr("id")) {
+            identified = true;
+            add(30, "\"logo\" in ancestor class/id", &mut evidence);
+        }
+    }
+
+    if !identified {
+        return (0, Vec::new());
+    }
+    (score, evidence)
+}
+
+/// Serialize an inline `<svg>` as a standalone, sanitized SVG document.
+fn standalone_svg(element: &ElementRef) -> Option<String> {
+    let mut markup = element.html();
+    // HTML parsing drops namespace declarations that XML consumers require.
+    if element.value().attr("xmlns").is_none() {
+        markup = markup.replacen("<svg", r#"<svg xmlns="http://www.w3.org/2000/svg""#, 1);
+    }
+    if markup.contains("xlink:") && !markup.contains("xmlns:xlink") {
+        markup = markup.replacen(
+            "<svg",
+            r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink""#,
+            1,
+        );
+    }
+    sanitize_svg(&markup).ok()
+}
+
+/// Heuristically find logo candidates, best first.
+pub(crate) fn detect_logos(document: &Html, base_url: &Url) -> Vec<LogoCandidate> {
+    let mut candidates: Vec<LogoCandidate> = Vec::new();
+
+    let selector = Selector::parse("img, svg").unwrap();
+    for element in document.select(&selector) {
+        let is_svg = element.value().name() == "svg";
+        // Only consider outermost SVGs.
+        if is_svg
+            && element
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|a| a.value().name() == "svg")
+        {
+            continue;
+        }
+
+        let (score, evidence) = gather_evidence(&element, base_url);
+        if score <= 0 {
+            continue;
+        }
+
+        let alt = element
+            .value()
+            .attr("alt")
+            .or_else(|| element.value().attr("aria-label"))
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty());
+
+        let candidate = if is_svg {
+            let svg = match standalone_svg(&element) {
+                Some(s) => s,
+                None => continue,
+            };
+            LogoCandidate {
+                url: None,
+                svg: Some(svg),
+                kind: "inline-svg".to_string(),
+                alt,
+                score,
+                evidence,
+            }
+        } else {
+            let src = element
+                .value()
+                .attr("src")
+                .or_else(|| element.value().attr("data-src"))
+                .map(str::trim)
+                .filter(|s| !s.is_empty() && !s.starts_with("data:"));
+            let url = match src.and_then(|s| base_url.join(s).ok()) {
+                Some(u) => u.to_string(),
+                None => continue,
+            };
+            if candidates.iter().any(|c| c.url.as_deref() == Some(&url)) {
+                continue;
+            }
+            LogoCandidate {
+                url: Some(url),
+                svg: None,
+                kind: "img".to_string(),
+                alt,
+                score,
+                evidence,
+            }
+        };
+        candidates.push(candidate);
+    }
+
+    // JSON-LD logos are explicit declarations; boost matching <img>s too.
+    for url in json_ld_logos(document, base_url) {
+        let reason = "declared as Organization.logo in JSON-LD".to_string();
+        match candidates
+            .iter_mut()
+            .find(|c| c.url.as_deref() == Some(&url))
+        {
+            Some(existing) => {
+                existing.score += 50;
+                existing.evidence.push(reason);
+            }
+            None => candidates.push(LogoCandidate {
+                url: Some(url),
+                svg: None,
+                kind: "json-ld".to_string(),
+                alt: None,
+                score: 50,
+                evidence: vec![reason],
+            }),
+        }
+    }
+
+    // Stable sort keeps document order among equal scores.
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.score));
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(html: &str) -> Vec<LogoCandidate> {
+        let base = Url::parse("https://example.com/about").unwrap();
+        detect_logos(&Html::parse_document(html), &base)
+    }
+
+    #[test]
+    fn header_logo_outranks_nav_icons() {
+        let candidates = detect(
+            r#"<body>
+            <nav>
+              <a href="/docs"><img src="/icons/docs.svg" alt="Docs"></a>
+              <a href="/search"><svg viewBox="0 0 16 16"><path d="M0 0h16v16z"/></svg></a>
+            </nav>
+            <header>
+              <a href="/"><img src="/brand.png" alt="Example Inc."></a>
+            </header>
+            </body>"#,
+        );
+        assert_eq!(candidates.len(), 1);
+        let logo = &candidates[0];
+        assert_eq!(logo.url.as_deref(), Some("https://example.com/brand.png"));
+        assert_eq!(logo.score, 35 + 20);
+        assert_eq!(
+            logo.evidence,
+            vec!["links to the homepage", "inside <header>"]
+        );
+    }
+
+    #[test]
+    fn page_regions_alone_are_not_evidence() {
+        let candidates = detect(
+            r#"<header role="banner"><img src="/hero.jpg"></header>
+            <nav><img src="/menu.svg"></nav>
+            <footer><img src="/partner-logo.png"></footer>"#,
+        );
+        assert!(candidates.is_empty());
+
+        let candidates = detect(r#"<nav><a href="/"><img src="/home.svg"></a></nav>"#);
+        assert_eq!(candidates[0].score, 35 + 10);
+    }
+
+    #[test]
+    fn inline_svg_logos_are_standalone_documents() {
+        let candidates = detect(
+            r#"<a href="/" class="site-logo"><svg viewBox="0 0 10 10"><svg><rect width="10" height="10"/></svg></svg></a>"#,
+        );
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].kind, "inline-svg");
+        let svg = candidates[0].svg.as_deref().unwrap();
+        assert!(svg.contains(r#"xmlns="http://www.w3.org/2000/svg""#));
+    }
+
+    #[test]
+    fn json_ld_logo_boosts_a_matching_img() {
+        let candidates = detect(
+            r#"<script type="application/ld+json">
+            {"@type": "Organization", "logo": "/brand.png"}
+            </script>
+            <img class="logo" src="/brand.png">
+            <img class="logo" src="/other.png">"#,
+        );
+        assert_eq!(
+            candidates[0].url.as_deref(),
+            Some("https://example.com/brand.png")
+        );
+        assert_eq!(candidates[0].score, 40 + 50);
+        assert_eq!(candidates[1].score, 40);
+    }
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::config::Config;
+use crate::credentials;
+use crate::diagnostics::{Diagnostic, Trace};
+use crate::error::ScanError;
+use crate::scanner::{FaviconInfo, ScanWarning};
+
+/// Metadata from a web app manifest (`<link rel="manifest">`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestInfo {
+    pub url: String,
+    pub name: Option<String>,
+    pub short_name: Option<String>,
+    pub theme_color: Option<String>,
+    pub background_color: Option<String>,
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Parse manifest JSON, resolving icon URLs relative to the manifest itself.
+///
+/// Parsing is lenient: malformed icon entries are skipped rather than
+/// failing the whole manifest.
+pub fn parse_manifest(
+    json: &str,
+    manifest_url: &Url,
+) -> Result<(ManifestInfo, Vec<FaviconInfo>), String> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| format!("Invalid manifest JSON: {}", e))?;
+
+    if !value.is_object() {
+        return Err("Invalid manifest JSON: expected an object".to_string());
+    }
+
+    let info = ManifestInfo {
+        url: manifest_url.to_string(),
+        name: string_field(&value, "name"),
+        short_name: string_field(&value, "short_name"),
+        theme_color: string_field(&value, "theme_color"),
+        background_color: string_field(&value, "background_color"),
+    };
+
+    let mut icons = Vec::new();
+    let entries = value
+        .get("icons")
+        .and_then(Value::as_array)
+        .map(|a| a.as_slice())
+        .unwrap_or_default();
+
+    for entry in entries {
+        let src = match string_field(entry, "src") {
+            Some(s) => s,
+            None => continue,
+        };
+        if src.starts_with("data:") {
+            continue;
+        }
+        let url = match manifest_url.join(&src) {
+            Ok(u) => u.to_string(),
+            Err(_) => continue,
+        };
+        if icons.iter().any(|i: &FaviconInfo| i.url == url) {
+            continue;
+        }
+
+        icons.push(FaviconInfo {
+            url,
+            rel: "manifest".to_string(),
+            sizes: string_field(entry, "sizes"),
+            mime_type: string_field(entry, "type"),
+            media: None,
+            color: None,
+            purpose: string_field(entry, "purpose"),
+            source: "manifest".to_string(),
+            details: None,
+        });
+    }
+
+    Ok((info, icons))
+}
+
+/// Fetch and parse a web app manifest. Returns `None` on any failure; the
+/// reason is recorded in `diagnostics`.
+pub(crate) async fn fetch_manifest(
+    cfg: &Config,
+    manifest_url: &str,
+    client: &reqwest::Client,
+    warnings: &mut Vec<ScanWarning>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<(ManifestInfo, Vec<FaviconInfo>)> {
+    let mut trace = Trace::new("manifest", manifest_url);
+    let result = load_manifest(cfg, manifest_url, client, warnings, &mut trace).await;
+    diagnostics.push(trace.finish());
+    result
+}
+
+async fn load_manifest(
+    cfg: &Config,
+    manifest_url: &str,
+    client: &reqwest::Client,
+    warnings: &mut Vec<ScanWarning>,
+    trace: &mut Trace,
+) -> Option<(ManifestInfo, Vec<FaviconInfo>)> {
+    if !trace.allowed(cfg).await {
+        return None;
+    }
+    let resp = trace
+        .send(
+            cfg,
+            credentials::get(cfg, client, manifest_url).timeout(cfg.timeout_request),
+        )
+        .await?;
+
+    // Use the final URL so icons resolve correctly after redirects.
+    let final_url = resp.url().clone();
+    let limit = cfg.max_html_size;
+    let body = trace.read(resp, limit).await?;
+    // A truncated manifest is not valid JSON.
+    if body.truncated {
+        warnings.push(ScanWarning::too_large("manifest", manifest_url, limit));
+        trace.fail(ScanError::TooLarge { limit });
+        return None;
+    }
+    trace.parsed(body.bytes.len());
+    let text = String::from_utf8_lossy(&body.bytes);
+
+    parse_manifest(&text, &final_url)
+        .map_err(|e| trace.fail(ScanError::Parse(e)))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest_url() -> Url {
+        Url::parse("https://example.com/app/site.webmanifest").unwrap()
+    }
+
+    #[test]
+    fn icon_sources_resolve_against_the_manifest_url() {
+        let json = r##"{
+            "name": " Example App ",
+            "short_name": "",
+            "theme_color": "#123456",
+            "icons": [
+                {"src": "icons/192.png", "sizes": "192x192", "type": "image/png"},
+                {"src": "/root.png", "sizes": "512x512", "purpose": "maskable"},
+                {"src": "../up.png", "purpose": "any monochrome"},
+                {"src": "https://cdn.example.net/abs.png"}
+            ]
+        }"##;
+        let (info, icons) = parse_manifest(json, &manifest_url()).unwrap();
+        assert_eq!(info.url, "https://example.com/app/site.webmanifest");
+        assert_eq!(info.name.as_deref(), Some("Example App"));
+        assert_eq!(info.short_name, None);
+        assert_eq!(info.theme_color.as_deref(), Some("#123456"));
+
+        let urls: Vec<&str> = icons.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/app/icons/192.png",
+                "https://example.com/root.png",
+                "https://example.com/up.png",
+                "https://cdn.example.net/abs.png",
+            ]
+        );
+        assert!(icons.iter().all(|i| i.rel == "manifest"));
+        assert!(icons.iter().all(|i| i.source == "manifest"));
+    }
+
+    #[test]
+    fn sizes_type_and_purpose_are_kept() {
+        let json = r#"{"icons": [
+            {"src": "a.png", "sizes": "48x48 96x96", "type": "image/png", "purpose": "maskable"},
+            {"src": "b.svg", "sizes": "any", "purpose": "  "}
+        ]}"#;
+        let (_, icons) = parse_manifest(json, &manifest_url()).unwrap();
+        assert_eq!(icons[0].sizes.as_deref(), Some("48x48 96x96"));
+        assert_eq!(icons[0].mime_type.as_deref(), Some("image/png"));
+        assert_eq!(icons[0].purpose.as_deref(), Some("maskable"));
+        assert_eq!(icons[1].sizes.as_deref(), Some("any"));
+        assert_eq!(icons[1].mime_type, None);
+        assert_eq!(icons[1].purpose, None);
+    }
+
+    #[test]
+    fn malformed_entries_are_skipped() {
+        let json = r#"{"icons": [
+            {"sizes": "16x16"},
+            {"src": 42},
+            {"src": "data:image/png;base64,AAAA"},
+            {"src": "ok.png"},
+            {"src": "./ok.png"}
+        ]}"#;
+        let (_, icons) = parse_manifest(json, &manifest_url()).unwrap();
+        assert_eq!(icons.len(), 1);
+        assert_eq!(icons[0].url, "https://example.com/app/ok.png");
+
+        assert!(parse_manifest("[]", &manifest_url()).is_err());
+        assert!(parse_manifest("{", &manifest_url()).is_err());
+    }
+}
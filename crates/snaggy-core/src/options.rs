@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::credentials::SiteCredentials;
+use crate::error::ScanError;
+
+/// A part of a scan that can be switched off to save requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Extractor {
+    /// Web app manifest icons and colors.
+    Manifest,
+    /// browserconfig.xml tile logos and color.
+    Browserconfig,
+    /// Probing the configured `well_known_icons` paths.
+    WellKnownIcons,
+    /// Downloading each icon to verify its format and size.
+    IconDetails,
+    /// External stylesheets: web fonts and most palette colors.
+    Stylesheets,
+    /// Open Graph, Twitter card and structured-data images.
+    Images,
+    /// On-page logo candidates.
+    Logos,
+}
+
+impl Extractor {
+    pub const ALL: [Extractor; 7] = [
+        Extractor::Manifest,
+        Extractor::Browserconfig,
+        Extractor::WellKnownIcons,
+        Extractor::IconDetails,
+        Extractor::Stylesheets,
+        Extractor::Images,
+        Extractor::Logos,
+    ];
+}
+
+/// Per-call overrides applied on top of a scanner's [`Config`].
+///
+/// Unset fields keep the configured value. Timeouts and limits can only be
+/// lowered, so a server can pass client-supplied options through as-is.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    pub user_agent: Option<String>,
+    /// Seconds.
+    pub timeout_request: Option<u64>,
+    /// Seconds.
+    pub timeout_image: Option<u64>,
+    pub max_stylesheets: Option<usize>,
+    pub max_imports: Option<usize>,
+    pub max_html_size: Option<u64>,
+    pub max_css_size: Option<u64>,
+    pub max_image_size: Option<u64>,
+    pub max_font_size: Option<u64>,
+    pub max_download_size: Option<u64>,
+    pub max_retries: Option<u32>,
+    /// Extractors to run; all of them when unset.
+    pub extractors: Option<Vec<Extractor>>,
+    /// Headers, cookies and credentials for specific hosts, added to the
+    /// configured ones. `cookies_file` is not accepted here.
+    pub credentials: Vec<SiteCredentials>,
+}
+
+fn lower<T: Ord + Copy>(current: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *current = (*current).min(value);
+    }
+}
+
+impl ScanOptions {
+    pub(crate) fn apply(&self, config: &mut Config) -> Result<(), ScanError> {
+        if let Some(user_agent) = &self.user_agent {
+            config.user_agent = user_agent.clone();
+        }
+        lower(
+            &mut config.timeout_request,
+            self.timeout_request.map(Duration::from_secs),
+        );
+        lower(
+            &mut config.timeout_image,
+            self.timeout_image.map(Duration::from_secs),
+        );
+        lower(&mut config.max_stylesheets, self.max_stylesheets);
+        lower(&mut config.max_imports, self.max_imports);
+        lower(&mut config.max_html_size, self.max_html_size);
+        lower(&mut config.max_css_size, self.max_css_size);
+        lower(&mut config.max_image_size, self.max_image_size);
+        lower(&mut config.max_font_size, self.max_font_size);
+        lower(&mut config.max_download_size, self.max_download_size);
+        lower(&mut config.max_retries, self.max_retries);
+
+        for site in &self.credentials {
+            if site.cookies_file.is_some() {
+                return Err(ScanError::InvalidRequest(
+                    "credentials: cookies_file is only allowed in the config file".to_string(),
+                ));
+            }
+            site.validate()
+                .map_err(|e| ScanError::InvalidRequest(format!("credentials: {}", e)))?;
+        }
+        config.credentials.extend(self.credentials.iter().cloned());
+        Ok(())
+    }
+}
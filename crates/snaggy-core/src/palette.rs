@@ -0,0 +1,734 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of custom properties kept per scan.
+const MAX_TOKENS: usize = 500;
+
+/// Maximum number of clustered palette colors returned.
+const MAX_COLORS: usize = 48;
+
+/// Longest `var()` expansion kept; longer token values are left unresolved.
+const MAX_RESOLVED_LEN: usize = 1024;
+
+/// Colors closer than this (redmean RGB distance) are merged into one.
+const CLUSTER_DISTANCE: f64 = 24.0;
+
+/// Sources that are explicit brand declarations rather than CSS usage.
+const DECLARED_SOURCES: &[&str] = &["theme-color", "manifest", "tile"];
+
+/// Brand colors and CSS design tokens found on the page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Palette {
+    /// Clustered colors, declared brand colors first, then by frequency.
+    pub colors: Vec<PaletteColor>,
+    /// CSS custom properties from `:root` and theme selectors.
+    pub tokens: Vec<DesignToken>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaletteColor {
+    /// Normalized `#rrggbb` (or `#rrggbbaa` when translucent).
+    pub hex: String,
+    pub count: usize,
+    /// Where the color was seen: "css", "theme-color", "manifest", "tile".
+    pub sources: Vec<String>,
+    /// Theme contexts the color was used in: "default", "light", "dark".
+    pub themes: Vec<String>,
+    /// Near-duplicate colors merged into this one.
+    pub merged: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DesignToken {
+    /// Custom property name, including the leading `--`.
+    pub name: String,
+    /// Raw declared value.
+    pub value: String,
+    /// Normalized color, if the value (after resolving `var()`) is one.
+    pub color: Option<String>,
+    /// "default", "light" or "dark".
+    pub theme: String,
+    pub selector: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rgba {
+    r: u8,
+    g: u8,
+    b: u8,
+    a: f64,
+}
+
+impl Rgba {
+    fn to_hex(self) -> String {
+        if self.a >= 1.0 {
+            format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        } else {
+            let a = (self.a.clamp(0.0, 1.0) * 255.0).round() as u8;
+            format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, a)
+        }
+    }
+
+    /// Cheap perceptual distance ("redmean" weighted RGB).
+    fn distance(self, other: Rgba) -> f64 {
+        let rmean = (self.r as f64 + other.r as f64) / 2.0;
+        let dr = self.r as f64 - other.r as f64;
+        let dg = self.g as f64 - other.g as f64;
+        let db = self.b as f64 - other.b as f64;
+        let da = (self.a - other.a) * 255.0;
+        (((512.0 + rmean) * dr * dr) / 256.0
+            + 4.0 * dg * dg
+            + ((767.0 - rmean) * db * db) / 256.0
+            + da * da)
+            .sqrt()
+    }
+}
+
+fn channel(v: f64) -> u8 {
+    v.round().clamp(0.0, 255.0) as u8
+}
+
+fn parse_hex(hex: &str) -> Option<Rgba> {
+    let digits = hex.strip_prefix('#')?;
+    let expanded: String = match digits.len() {
+        3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => digits.to_string(),
+        _ => return None,
+    };
+    let n = u32::from_str_radix(&expanded, 16).ok()?;
+    let (rgb, a) = if expanded.len() == 8 {
+        (n >> 8, (n & 0xFF) as f64 / 255.0)
+    } else {
+        (n, 1.0)
+    };
+    Some(Rgba {
+        r: (rgb >> 16) as u8,
+        g: (rgb >> 8) as u8,
+        b: rgb as u8,
+        a,
+    })
+}
+
+/// Parse a number or percentage; percentages are returned scaled by `pct_scale`.
+fn parse_component(token: &str, pct_scale: f64) -> Option<f64> {
+    let t = token.trim();
+    if t.eq_ignore_ascii_case("none") {
+        return Some(0.0);
+    }
+    match t.strip_suffix('%') {
+        Some(p) => p.parse::<f64>().ok().map(|v| v / 100.0 * pct_scale),
+        None => t.parse::<f64>().ok(),
+    }
+}
+
+fn parse_hue(token: &str) -> Option<f64> {
+    let t = token.trim().to_lowercase();
+    if let Some(v) = t.strip_suffix("deg") {
+        v.parse().ok()
+    } else if let Some(v) = t.strip_suffix("turn") {
+        v.parse::<f64>().ok().map(|v| v * 360.0)
+    } else if let Some(v) = t.strip_suffix("grad") {
+        v.parse::<f64>().ok().map(|v| v * 0.9)
+    } else if let Some(v) = t.strip_suffix("rad") {
+        v.parse::<f64>().ok().map(f64::to_degrees)
+    } else if t == "none" {
+        Some(0.0)
+    } else {
+        t.parse().ok()
+    }
+}
+
+fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (f64, f64, f64) {
+    let h = h.rem_euclid(360.0) / 360.0;
+    let s = s.clamp(0.0, 1.0);
+    let l = l.clamp(0.0, 1.0);
+    if s == 0.0 {
+        return (l * 255.0, l * 255.0, l * 255.0);
+    }
+    let q = if l < 0.5 {
+        l * (1.0 + s)
+    } else {
+        l + s - l * s
+    };
+    let p = 2.0 * l - q;
+    let hue = |mut t: f64| {
+        t = t.rem_euclid(1.0);
+        if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        }
+    };
+    (
+        hue(h + 1.0 / 3.0) * 255.0,
+        hue(h) * 255.0,
+        hue(h - 1.0 / 3.0) * 255.0,
+    )
+}
+
+fn oklch_to_rgb(l: f64, c: f64, h: f64) -> (f64, f64, f64) {
+    let (a, b) = (c * h.to_radians().cos(), c * h.to_radians().sin());
+    let l_ = (l + 0.396_337_777_4 * a + 0.215_803_757_3 * b).powi(3);
+    let m_ = (l - 0.105_561_345_8 * a - 0.063_854_172_8 * b).powi(3);
+    let s_ = (l - 0.089_484_177_5 * a - 1.291_485_548 * b).powi(3);
+
+    let r = 4.076_741_662_1 * l_ - 3.307_711_591_3 * m_ + 0.230_969_929_2 * s_;
+    let g = -1.268_438_004_6 * l_ + 2.609_757_401_1 * m_ - 0.341_319_396_5 * s_;
+    let b = -0.004_196_086_3 * l_ - 0.703_418_614_7 * m_ + 1.707_614_701 * s_;
+
+    let gamma = |x: f64| {
+        let x = x.clamp(0.0, 1.0);
+        if x <= 0.003_130_8 {
+            12.92 * x
+        } else {
+            1.055 * x.powf(1.0 / 2.4) - 0.055
+        }
+    };
+    (gamma(r) * 255.0, gamma(g) * 255.0, gamma(b) * 255.0)
+}
+
+/// Parse a single CSS color value: hex, rgb(a), hsl(a) or oklch.
+fn parse_color(value: &str) -> Option<Rgba> {
+    let v = value.trim();
+    if v.starts_with('#') {
+        return parse_hex(v);
+    }
+
+    let open = v.find('(')?;
+    let func = v[..open].trim().to_lowercase();
+    let args = v[open + 1..].strip_suffix(')')?;
+    let (channels, alpha) = match args.split_once('/') {
+        Some((c, a)) => (c, Some(a)),
+        None => (args, None),
+    };
+    let mut parts: Vec<&str> = channels
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .collect();
+    // Legacy comma syntax carries alpha as a fourth argument.
+    let alpha = match alpha {
+        Some(a) => parse_component(a, 1.0)?,
+        None if parts.len() == 4 => parse_component(parts.pop()?, 1.0)?,
+        None => 1.0,
+    };
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let (r, g, b) = match func.as_str() {
+        "rgb" | "rgba" => (
+            parse_component(parts[0], 255.0)?,
+            parse_component(parts[1], 255.0)?,
+            parse_component(parts[2], 255.0)?,
+        ),
+        "hsl" | "hsla" => hsl_to_rgb(
+            parse_hue(parts[0])?,
+            parse_component(parts[1], 1.0)? / if parts[1].ends_with('%') { 1.0 } else { 100.0 },
+            parse_component(parts[2], 1.0)? / if parts[2].ends_with('%') { 1.0 } else { 100.0 },
+        ),
+        "oklch" => oklch_to_rgb(
+            parse_component(parts[0], 1.0)?,
+            parse_component(parts[1], 0.4)?,
+            parse_hue(parts[2])?,
+        ),
+        _ => return None,
+    };
+
+    Some(Rgba {
+        r: channel(r),
+        g: channel(g),
+        b: channel(b),
+        a: alpha.clamp(0.0, 1.0),
+    })
+}
+
+/// A style rule with the `@media` condition it was nested in, if any.
+struct CssRule {
+    selector: String,
+    media: Option<String>,
+    body: String,
+}
+
+/// Split a stylesheet into style rules, descending into `@media`,
+/// `@supports`, `@layer` and `@container` blocks.
+fn walk_rules(css: &str, media: Option<&str>, out: &mut Vec<CssRule>) {
+    let mut rest = css;
+    while let Some(open) = rest.find('{') {
+        // Statements like `@import …;` may precede the prelude.
+        let prelude = rest[..open].rsplit([';', '}']).next().unwrap_or("").trim();
+        let body_start = open + 1;
+        let mut depth = 1;
+        let mut body_end = rest.len();
+        for (i, ch) in rest[body_start..].char_indices() {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        body_end = body_start + i;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let body = &rest[body_start..body_end];
+
+        let lower = prelude.to_lowercase();
+        if lower.starts_with("@media") {
+            walk_rules(body, Some(prelude), out);
+        } else if ["@supports", "@layer", "@container"]
+            .iter()
+            .any(|p| lower.starts_with(p))
+        {
+            walk_rules(body, media, out);
+        } else if !lower.starts_with('@') {
+            out.push(CssRule {
+                selector: prelude.to_string(),
+                media: media.map(|m| m.to_string()),
+                body: body.to_string(),
+            });
+        }
+
+        rest = &rest[(body_end + 1).min(rest.len())..];
+    }
+}
+
+/// Selector words that mark a theme scope, as in `.dark`, `.theme-dark` or
+/// `[data-theme="dark"]`.
+const DARK_WORDS: &[&str] = &["dark", "theme-dark", "dark-theme", "dark-mode"];
+const LIGHT_WORDS: &[&str] = &["light", "theme-light", "light-theme", "light-mode"];
+
+/// The theme a `prefers-color-scheme` media query selects, if any. A
+/// leading `not` selects the other theme.
+pub(crate) fn media_theme(media: &str) -> Option<&'static str> {
+    let media = media.to_lowercase().replace(char::is_whitespace, "");
+    let (_, rest) = media.split_once("prefers-color-scheme:")?;
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .unwrap_or(rest.len());
+    let negated = media.starts_with("not");
+    match (&rest[..end], negated) {
+        ("dark", false) | ("light", true) => Some("dark"),
+        ("light", false) | ("dark", true) => Some("light"),
+        _ => None,
+    }
+}
+
+/// The theme a selector scopes to. Only whole class names and attribute
+/// values count, so `.highlight` or `.darken-btn` stay "default".
+fn selector_theme(selector: &str) -> Option<&'static str> {
+    let selector = selector.to_lowercase();
+    let words: Vec<&str> = selector
+        .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_')
+        .collect();
+    if words.iter().any(|w| DARK_WORDS.contains(w)) {
+        Some("dark")
+    } else if words.iter().any(|w| LIGHT_WORDS.contains(w)) {
+        Some("light")
+    } else {
+        None
+    }
+}
+
+/// Classify a rule's theme from its media query and selector.
+fn rule_theme(selector: &str, media: Option<&str>) -> &'static str {
+    media
+        .and_then(media_theme)
+        .or_else(|| selector_theme(selector))
+        .unwrap_or("default")
+}
+
+/// Selectors whose custom properties count as design tokens.
+fn is_token_selector(selector: &str) -> bool {
+    selector.split(',').any(|part| {
+        let p = part.trim().to_lowercase();
+        p.starts_with(":root")
+            || p == "html"
+            || p.starts_with("html[")
+            || p.starts_with("html.")
+            || p.starts_with("[data-theme")
+            || p.starts_with("[data-mode")
+            || p.starts_with("[data-color-mode")
+            || p.starts_with("[data-color-scheme")
+            || p == ".dark"
+            || p == ".light"
+            || p.starts_with(".theme-")
+    })
+}
+
+/// Accumulates colors and tokens across every stylesheet of a scan.
+pub(crate) struct PaletteBuilder {
+    counts: HashMap<String, usize>,
+    sources: HashMap<String, BTreeSet<String>>,
+    themes: HashMap<String, BTreeSet<String>>,
+    tokens: Vec<DesignToken>,
+    comment_re: Regex,
+    color_re: Regex,
+    var_re: Regex,
+    channels_re: Regex,
+}
+
+/// Tokens resolved so far, keyed by name and theme, and the ones currently
+/// being expanded. `None` marks a cycle or an over-long expansion.
+#[derive(Default)]
+struct Resolution {
+    resolved: HashMap<(String, String), Option<String>>,
+    visiting: HashSet<(String, String)>,
+}
+
+impl PaletteBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            counts: HashMap::new(),
+            sources: HashMap::new(),
+            themes: HashMap::new(),
+            tokens: Vec::new(),
+            comment_re: Regex::new(r"(?s)/\*.*?\*/").unwrap(),
+            color_re: Regex::new(r"(?i)#[0-9a-f]{3,8}\b|\b(?:rgba?|hsla?|oklch)\([^()]*\)")
+                .unwrap(),
+            var_re: Regex::new(r"var\(\s*(--[\w-]+)\s*(?:,\s*([^()]*))?\)").unwrap(),
+            channels_re: Regex::new(r"^\s*-?[\d.]+(?:deg)?\s+[\d.]+%\s+[\d.]+%\s*$").unwrap(),
+        }
+    }
+
+    fn record(&mut self, color: Rgba, source: &str, theme: &str) {
+        // Fully transparent colors carry no brand information.
+        if color.a <= 0.0 {
+            return;
+        }
+        let hex = color.to_hex();
+        *self.counts.entry(hex.clone()).or_default() += 1;
+        self.sources
+            .entry(hex.clone())
+            .or_default()
+            .insert(source.to_string());
+        self.themes
+            .entry(hex)
+            .or_default()
+            .insert(theme.to_string());
+    }
+
+    /// Record an explicitly declared color (theme-color, manifest, tile).
+    pub(crate) fn add_declared(&mut self, value: &str, source: &str, theme: &str) {
+        if let Some(color) = parse_color(value) {
+            self.record(color, source, theme);
+        }
+    }
+
+    /// Record colors used in a declaration block (or a `style` attribute).
+    pub(crate) fn add_declarations(&mut self, body: &str, theme: &str) {
+        let mut found = Vec::new();
+        for declaration in body.split(';') {
+            let Some((_, value)) = declaration.split_once(':') else {
+                continue;
+            };
+            found.extend(
+                self.color_re
+                    .find_iter(value)
+                    .filter_map(|m| parse_color(m.as_str())),
+            );
+        }
+        for color in found {
+            self.record(color, "css", theme);
+        }
+    }
+
+    /// Record every color and design token in a stylesheet.
+    pub(crate) fn add_css(&mut self, css: &str) {
+        let css = self.comment_re.replace_all(css, "").into_owned();
+
+        let mut rules = Vec::new();
+        walk_rules(&css, None, &mut rules);
+
+        for rule in rules {
+            let theme = rule_theme(&rule.selector, rule.media.as_deref());
+            self.add_declarations(&rule.body, theme);
+
+            if !is_token_selector(&rule.selector) {
+                continue;
+            }
+            for declaration in rule.body.split(';') {
+                let Some((name, value)) = declaration.split_once(':') else {
+                    continue;
+                };
+                let name = name.trim();
+                if !name.starts_with("--") || self.tokens.len() >= MAX_TOKENS {
+                    continue;
+                }
+                self.tokens.push(DesignToken {
+                    name: name.to_string(),
+                    value: value.trim().to_string(),
+                    color: None,
+                    theme: theme.to_string(),
+                    selector: rule.selector.clone(),
+                });
+            }
+        }
+    }
+
+    /// Resolve `var()` references, preferring tokens from the same theme and
+    /// falling back to the default theme. Unknown, cyclic or over-long
+    /// references use their fallback. Returns `None` if one has none, or if
+    /// the expansion exceeds `MAX_RESOLVED_LEN`.
+    fn resolve_value(&self, value: &str, theme: &str, state: &mut Resolution) -> Option<String> {
+        let mut out = String::new();
+        let mut last = 0;
+        for caps in self.var_re.captures_iter(value) {
+            let whole = caps.get(0).unwrap();
+            out.push_str(&value[last..whole.start()]);
+            last = whole.end();
+            // Like CSS, a reference that can't resolve and has no fallback
+            // invalidates the whole value.
+            match self.resolve_token(&caps[1], theme, state) {
+                Some(resolved) => out.push_str(&resolved),
+                None => out.push_str(caps.get(2)?.as_str().trim()),
+            }
+            if out.len() > MAX_RESOLVED_LEN {
+                return None;
+            }
+        }
+        out.push_str(&value[last..]);
+        (out.len() <= MAX_RESOLVED_LEN).then_some(out)
+    }
+
+    /// Resolve one custom property for a theme, at most once per scan.
+    fn resolve_token(&self, name: &str, theme: &str, state: &mut Resolution) -> Option<String> {
+        let key = (name.to_string(), theme.to_string());
+        if let Some(resolved) = state.resolved.get(&key) {
+            return resolved.clone();
+        }
+        if !state.visiting.insert(key.clone()) {
+            return None;
+        }
+
+        let lookup = |t: &str| {
+            self.tokens
+                .iter()
+                .rev()
+                .find(|tok| tok.name == name && tok.theme == t)
+        };
+        let resolved = lookup(theme)
+            .or_else(|| lookup("default"))
+            .and_then(|tok| self.resolve_value(&tok.value, theme, state));
+
+        state.visiting.remove(&key);
+        state.resolved.insert(key, resolved.clone());
+        resolved
+    }
+
+    /// Parse a token value as a color, also accepting bare HSL channels like
+    /// `222.2 47.4% 11.2%` (the shadcn/ui convention for `hsl(var(--x))`).
+    fn token_color(&self, value: &str) -> Option<Rgba> {
+        parse_color(value).or_else(|| {
+            self.channels_re
+                .is_match(value)
+                .then(|| parse_color(&format!("hsl({})", value)))
+                .flatten()
+        })
+    }
+
+    pub(crate) fn build(mut self) -> Palette {
+        let mut state = Resolution::default();
+        let resolved: Vec<Option<String>> = self
+            .tokens
+            .iter()
+            .map(|tok| {
+                let value = self.resolve_value(&tok.value, &tok.theme, &mut state)?;
+                self.token_color(&value).map(|c| c.to_hex())
+            })
+            .collect();
+        for (tok, color) in self.tokens.iter_mut().zip(resolved) {
+            tok.color = color;
+        }
+
+        let declared = |sources: &BTreeSet<String>| {
+            sources
+                .iter()
+                .any(|s| DECLARED_SOURCES.contains(&s.as_str()))
+        };
+        let mut entries: Vec<(String, usize)> = self.counts.into_iter().collect();
+        entries.sort_by(|a, b| {
+            let da = self.sources.get(&a.0).is_some_and(declared);
+            let db = self.sources.get(&b.0).is_some_and(declared);
+            db.cmp(&da).then(b.1.cmp(&a.1)).then(a.0.cmp(&b.0))
+        });
+
+        // Greedy clustering: each color joins the first (more prominent)
+        // cluster it is close to.
+        let mut colors: Vec<(Rgba, PaletteColor)> = Vec::new();
+        for (hex, count) in entries {
+            let Some(rgba) = parse_hex(&hex) else {
+                continue;
+            };
+            let sources = self.sources.remove(&hex).unwrap_or_default();
+            let themes = self.themes.remove(&hex).unwrap_or_default();
+
+            match colors
+                .iter_mut()
+                .find(|(c, _)| c.distance(rgba) < CLUSTER_DISTANCE)
+            {
+                Some((_, cluster)) => {
+                    cluster.count += count;
+                    cluster.merged.push(hex);
+                    for s in sources {
+                        if !cluster.sources.contains(&s) {
+                            cluster.sources.push(s);
+                        }
+                    }
+                    for t in themes {
+                        if !cluster.themes.contains(&t) {
+                            cluster.themes.push(t);
+                        }
+                    }
+                }
+                None => colors.push((
+                    rgba,
+                    PaletteColor {
+                        hex,
+                        count,
+                        sources: sources.into_iter().collect(),
+                        themes: themes.into_iter().collect(),
+                        merged: Vec::new(),
+                    },
+                )),
+            }
+        }
+
+        let mut colors: Vec<PaletteColor> = colors.into_iter().map(|(_, c)| c).collect();
+        colors.truncate(MAX_COLORS);
+
+        Palette {
+            colors,
+            tokens: self.tokens,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_match_whole_words_only() {
+        assert_eq!(rule_theme(".dark", None), "dark");
+        assert_eq!(rule_theme("html.dark .card", None), "dark");
+        assert_eq!(rule_theme("[data-theme=\"dark\"]", None), "dark");
+        assert_eq!(rule_theme("[data-mode='light']", None), "light");
+        assert_eq!(rule_theme(".theme-light", None), "light");
+        assert_eq!(rule_theme(".highlight", None), "default");
+        assert_eq!(rule_theme(".darken-btn", None), "default");
+        assert_eq!(rule_theme(".lightbox img", None), "default");
+        assert_eq!(rule_theme(".btn-dark", None), "default");
+    }
+
+    fn token_colors(css: &str) -> Vec<(String, String, Option<String>)> {
+        let mut builder = PaletteBuilder::new();
+        builder.add_css(css);
+        builder
+            .build()
+            .tokens
+            .into_iter()
+            .map(|t| (t.name, t.theme, t.color))
+            .collect()
+    }
+
+    fn color_of(
+        tokens: &[(String, String, Option<String>)],
+        name: &str,
+        theme: &str,
+    ) -> Option<String> {
+        tokens
+            .iter()
+            .find(|(n, t, _)| n == name && t == theme)
+            .and_then(|(_, _, c)| c.clone())
+    }
+
+    #[test]
+    fn var_references_resolve_through_themes() {
+        let tokens = token_colors(
+            ":root { --brand: #ff0000; --accent: var(--brand); --muted: var(--missing, #00ff00); }
+             .dark { --brand: hsl(240 100% 50%); --accent: var(--brand); }
+             /* :root { --brand: #123456; } */",
+        );
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(
+            color_of(&tokens, "--accent", "default").as_deref(),
+            Some("#ff0000")
+        );
+        assert_eq!(
+            color_of(&tokens, "--accent", "dark").as_deref(),
+            Some("#0000ff")
+        );
+        assert_eq!(
+            color_of(&tokens, "--muted", "default").as_deref(),
+            Some("#00ff00")
+        );
+    }
+
+    #[test]
+    fn var_cycles_fall_back_instead_of_recursing() {
+        let tokens = token_colors(
+            ":root { --a: var(--b); --b: var(--a); --self: var(--self, #0000ff); --c: var(--a, #ff0000); }",
+        );
+        assert_eq!(color_of(&tokens, "--a", "default"), None);
+        assert_eq!(color_of(&tokens, "--b", "default"), None);
+        assert_eq!(
+            color_of(&tokens, "--self", "default").as_deref(),
+            Some("#0000ff")
+        );
+        assert_eq!(
+            color_of(&tokens, "--c", "default").as_deref(),
+            Some("#ff0000")
+        );
+    }
+
+    #[test]
+    fn exponential_var_expansion_is_cut_off() {
+        // Each level doubles the previous one: 2^40 references when expanded naively.
+        let mut css = String::from(":root { --v0: #abcdef;");
+        for i in 1..=40 {
+            css.push_str(&format!(" --v{}: var(--v{}) var(--v{});", i, i - 1, i - 1));
+        }
+        css.push_str(" --top: var(--v40, #123456); }");
+
+        let tokens = token_colors(&css);
+        assert_eq!(
+            color_of(&tokens, "--v0", "default").as_deref(),
+            Some("#abcdef")
+        );
+        assert_eq!(color_of(&tokens, "--v40", "default"), None);
+        assert_eq!(
+            color_of(&tokens, "--top", "default").as_deref(),
+            Some("#123456")
+        );
+    }
+
+    #[test]
+    fn media_queries_pick_the_scheme() {
+        assert_eq!(media_theme("(prefers-color-scheme: dark)"), Some("dark"));
+        assert_eq!(
+            media_theme("screen and (PREFERS-COLOR-SCHEME:light)"),
+            Some("light")
+        );
+        assert_eq!(media_theme("(prefers-color-scheme: darkish)"), None);
+        assert_eq!(
+            media_theme("not (prefers-color-scheme: dark)"),
+            Some("light")
+        );
+        assert_eq!(
+            media_theme("(max-width: 600px) and (min-resolution: 2dppx)"),
+            None
+        );
+        assert_eq!(
+            rule_theme(".highlight", Some("(prefers-color-scheme: dark)")),
+            "dark"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::LazyLock;
+
+use futures::future::join_all;
+use regex::Regex;
+use reqwest::StatusCode;
+use reqwest::header::RANGE;
+use url::Url;
+
+use crate::config::Config;
+use crate::credentials;
+use crate::diagnostics::{Diagnostic, Trace};
+use crate::error::ScanError;
+use crate::retry;
+use crate::scanner::FaviconInfo;
+
+/// How many leading bytes are fetched to identify an icon's format.
+const SNIFF_BYTES: usize = 512;
+
+/// BITMAPINFOHEADER and its variants, by header size.
+const BMP_HEADER_SIZES: [u32; 7] = [12, 40, 52, 56, 64, 108, 124];
+
+static SIZES_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+)x(\d+)").unwrap());
+
+/// Identify an image format from its leading bytes, returning its MIME type.
+pub fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+        Some("image/x-icon")
+    } else if bytes.starts_with(&[0x00, 0x00, 0x02, 0x00]) {
+        Some("image/x-win-bitmap")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"icns") {
+        Some("image/icns")
+    } else if bytes.starts_with(b"BM") && has_bmp_header(bytes) {
+        Some("image/bmp")
+    } else if looks_like_svg(bytes) {
+        Some("image/svg+xml")
+    } else {
+        None
+    }
+}
+
+/// A `BM` prefix alone matches plenty of text; require a known DIB header
+/// size after the 14-byte file header.
+fn has_bmp_header(bytes: &[u8]) -> bool {
+    bytes
+        .get(14..18)
+        .map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+        .is_some_and(|size| BMP_HEADER_SIZES.contains(&size))
+}
+
+/// SVG is text, so look past any BOM, XML declaration, comments or doctype
+/// for an `<svg` root. HTML error pages start with `<!doctype html`/`<html`.
+fn looks_like_svg(bytes: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(bytes).to_lowercase();
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with("<svg") {
+        return true;
+    }
+    (text.starts_with("<?xml") || text.starts_with("<!--") || text.starts_with("<!doctype svg"))
+        && text.contains("<svg")
+}
+
+fn rel_for_path(path: &str) -> &'static str {
+    if path.contains("apple-touch-icon-precomposed") {
+        "apple-touch-icon-precomposed"
+    } else if path.contains("apple-touch-icon") {
+        "apple-touch-icon"
+    } else {
+        "icon"
+    }
+}
+
+fn sizes_from_path(path: &str) -> Option<String> {
+    SIZES_RE
+        .captures(path)
+        .map(|c| format!("{}x{}", &c[1], &c[2]))
+}
+
+/// Probe one well-known icon URL.
+///
+/// HEAD is used as a cheap existence check, so a 404 or 410 costs no GET.
+/// Anything else gets a ranged GET, and the icon only counts if its leading
+/// bytes are a recognised image format: content types lie, and soft-404
+/// pages often come back as `200 text/html`.
+async fn probe_icon(
+    cfg: &Config,
+    url: String,
+    path: String,
+    client: &reqwest::Client,
+    trace: &mut Trace,
+) -> Option<FaviconInfo> {
+    let (head, retries) = retry::send(
+        cfg,
+        credentials::head(cfg, client, &url).timeout(cfg.timeout_probe),
+    )
+    .await;
+    trace.retried(retries);
+    if let Ok(resp) = &head
+        && matches!(resp.status(), StatusCode::NOT_FOUND | StatusCode::GONE)
+    {
+        trace.response(resp);
+        trace.fail(ScanError::upstream_status(url, resp.status()));
+        return None;
+    }
+
+    let mut resp = trace
+        .send(
+            cfg,
+            credentials::get(cfg, client, &url)
+                .header(RANGE, format!("bytes=0-{}", SNIFF_BYTES - 1))
+                .timeout(cfg.timeout_probe),
+        )
+        .await?;
+
+    // Servers that ignore Range send the whole file; stop once we have enough.
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    while head.len() < SNIFF_BYTES {
+        match resp.chunk().await {
+            Ok(Some(chunk)) => head.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(e) => {
+                trace.fail(ScanError::from_request(e));
+                return None;
+            }
+        }
+    }
+    trace.received(head.len());
+    trace.parsed(head.len());
+
+    let Some(mime_type) = sniff_image_type(&head) else {
+        trace.fail(ScanError::Parse("Not a recognized image".to_string()));
+        return None;
+    };
+
+    Some(FaviconInfo {
+        url,
+        rel: rel_for_path(&path).to_string(),
+        sizes: sizes_from_path(&path),
+        mime_type: Some(mime_type.to_string()),
+        media: None,
+        color: None,
+        purpose: None,
+        source: "well-known".to_string(),
+        details: None,
+    })
+}
+
+/// Concurrently probe the configured well-known icon paths, skipping any URL
+/// already discovered. Returns the icons found and a diagnostic per probe.
+pub(crate) async fn probe_well_known_icons(
+    cfg: &Config,
+    base_url: &Url,
+    known: &[FaviconInfo],
+    client: &reqwest::Client,
+) -> (Vec<FaviconInfo>, Vec<Diagnostic>) {
+    let mut targets: Vec<(String, String)> = Vec::new();
+    for path in &cfg.well_known_icons {
+        let url = match base_url.join(path) {
+            Ok(u) => u.to_string(),
+            Err(_) => continue,
+        };
+        if known.iter().any(|f| f.url == url) || targets.iter().any(|(u, _)| *u == url) {
+            continue;
+        }
+        targets.push((url, path.clone()));
+    }
+
+    let probes = targets.into_iter().map(|(url, path)| async move {
+        let mut trace = Trace::new("icon-probe", &url);
+        let icon = probe_icon(cfg, url, path, client, &mut trace).await;
+        (icon, trace.finish())
+    });
+
+    let (icons, diagnostics): (Vec<_>, Vec<_>) = join_all(probes).await.into_iter().unzip();
+    (icons.into_iter().flatten().collect(), diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn ico_and_cur_are_told_apart() {
+        assert_eq!(sniff_image_type(&[0, 0, 1, 0, 1, 0]), Some("image/x-icon"));
+        assert_eq!(
+            sniff_image_type(&[0, 0, 2, 0, 1, 0]),
+            Some("image/x-win-bitmap")
+        );
+    }
+
+    #[test]
+    fn bmp_needs_a_dib_header() {
+        let mut bmp = b"BM".to_vec();
+        bmp.resize(14, 0);
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        assert_eq!(sniff_image_type(&bmp), Some("image/bmp"));
+
+        assert_eq!(sniff_image_type(b"BMW owners club, est. 1989"), None);
+        assert_eq!(sniff_image_type(b"BM"), None);
+    }
+
+    #[test]
+    fn sizes_come_from_the_path() {
+        assert_eq!(
+            sizes_from_path("/apple-touch-icon-180x180.png").as_deref(),
+            Some("180x180")
+        );
+        assert_eq!(sizes_from_path("/favicon.ico"), None);
+    }
+
+    /// Claims every path is a PNG, but only `/real.png` starts like one.
+    async fn lying_server() -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let body: &[u8] = if request.contains(" /real.png ") {
+                    b"\x89PNG\r\n\x1a\n"
+                } else {
+                    b"<!doctype html><p>Not found</p>"
+                };
+                let mut response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: image/png\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                )
+                .into_bytes();
+                if !request.starts_with("HEAD") {
+                    response.extend_from_slice(body);
+                }
+                let _ = socket.write_all(&response).await;
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn image_content_type_alone_is_not_enough() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let base = lying_server().await;
+            let scanner = Scanner::new(Config {
+                allow_private_networks: true,
+                well_known_icons: vec!["/fake.png".to_string(), "/real.png".to_string()],
+                ..Config::default()
+            })
+            .unwrap();
+            let (icons, diagnostics) =
+                probe_well_known_icons(scanner.config(), &base, &[], scanner.client()).await;
+            let urls: Vec<&str> = icons.iter().map(|i| i.url.as_str()).collect();
+            assert_eq!(urls, vec![base.join("/real.png").unwrap().as_str()]);
+            assert_eq!(diagnostics[0].error_code.as_deref(), Some("parse"));
+        });
+    }
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::ScanError;
+use crate::scanner::{FaviconInfo, Scanner};
+
+/// An icon together with its ranking score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedIcon {
+    pub icon: FaviconInfo,
+    pub score: i32,
+    /// Pixel size used for ranking: decoded where possible, otherwise the
+    /// largest declared size. `None` for SVGs and unknown sizes.
+    pub effective_size: Option<u32>,
+}
+
+fn is_svg(icon: &FaviconInfo) -> bool {
+    let mime = icon
+        .details
+        .as_ref()
+        .map(|d| d.mime_type.as_str())
+        .or(icon.mime_type.as_deref())
+        .unwrap_or_default();
+    mime.contains("svg")
+        || icon
+            .url
+            .to_lowercase()
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .ends_with(".svg")
+}
+
+fn effective_size(icon: &FaviconInfo) -> Option<u32> {
+    if is_svg(icon) {
+        return None;
+    }
+    if let Some(d) = &icon.details
+        && let (Some(w), Some(h)) = (d.width, d.height)
+    {
+        return Some(w.min(h));
+    }
+    icon.sizes
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|token| {
+            let (w, h) = token
+                .to_lowercase()
+                .split_once('x')
+                .map(|(w, h)| (w.parse::<u32>().ok(), h.parse::<u32>().ok()))?;
+            Some(w?.min(h?))
+        })
+        .max()
+}
+
+fn format_score(icon: &FaviconInfo, size: Option<u32>) -> i32 {
+    if is_svg(icon) {
+        return 300;
+    }
+    let mime = icon
+        .details
+        .as_ref()
+        .map(|d| d.mime_type.clone())
+        .or_else(|| icon.mime_type.clone())
+        .unwrap_or_default();
+    match mime.as_str() {
+        "image/png" if size.unwrap_or(0) >= 128 => 220,
+        "image/png" | "image/webp" => 180,
+        "image/x-icon" | "image/vnd.microsoft.icon" | "image/x-win-bitmap" | "image/icns" => 120,
+        "image/gif" | "image/jpeg" => 80,
+        _ => 40,
+    }
+}
+
+/// Score how well the icon's pixel size serves the target. Upscaling is
+/// penalized much harder than downscaling.
+fn size_score(icon: &FaviconInfo, size: Option<u32>, target_size: Option<u32>) -> i32 {
+    if is_svg(icon) {
+        return 400;
+    }
+    let size = match size {
+        Some(s) if s > 0 => s as i64,
+        _ => return 0,
+    };
+    let score = match target_size.map(|t| t.max(1) as i64) {
+        Some(t) if size >= t => 400 - ((size - t) * 100 / t).min(150),
+        Some(t) => 400 * size / t - 200,
+        None => size.min(512) * 400 / 512,
+    };
+    score as i32
+}
+
+/// Monochrome silhouettes and padded tiles make poor general-purpose icons.
+fn rel_score(icon: &FaviconInfo) -> i32 {
+    let rel = icon.rel.to_lowercase();
+    if rel.contains("mask-icon") {
+        -400
+    } else if rel.contains("apple-touch-icon") {
+        50
+    } else if rel == "manifest" {
+        40
+    } else if rel.contains("icon") {
+        30
+    } else {
+        -50
+    }
+}
+
+fn purpose_score(icon: &FaviconInfo) -> i32 {
+    let purpose = icon.purpose.as_deref().unwrap_or("any").to_lowercase();
+    let tokens: Vec<&str> = purpose.split_whitespace().collect();
+    if tokens.is_empty() || tokens.contains(&"any") {
+        0
+    } else if tokens.contains(&"monochrome") {
+        -300
+    } else {
+        // Maskable icons carry safe-zone padding around the artwork.
+        -150
+    }
+}
+
+/// Score one icon. Higher is better; icons that couldn't be fetched and
+/// decoded sink below every reachable one.
+pub fn score_icon(icon: &FaviconInfo, target_size: Option<u32>) -> i32 {
+    let size = effective_size(icon);
+    let mut score = format_score(icon, size)
+        + size_score(icon, size, target_size)
+        + rel_score(icon)
+        + purpose_score(icon);
+
+    match &icon.details {
+        Some(d) if d.size_mismatch => score -= 50,
+        Some(_) => {}
+        None => score -= 1000,
+    }
+    score
+}
+
+/// Rank icons best-first for a target pixel size (or "largest" when `None`).
+pub fn rank_icons(icons: &[FaviconInfo], target_size: Option<u32>) -> Vec<RankedIcon> {
+    let mut ranked: Vec<RankedIcon> = icons
+        .iter()
+        .map(|icon| RankedIcon {
+            icon: icon.clone(),
+            score: score_icon(icon, target_size),
+            effective_size: effective_size(icon),
+        })
+        .collect();
+    ranked.sort_by_key(|r| std::cmp::Reverse(r.score));
+    ranked
+}
+
+/// Scan a website and return its best icon for the target size.
+pub async fn best_icon(
+    scanner: &Scanner,
+    url: &str,
+    target_size: Option<u32>,
+) -> Result<RankedIcon, ScanError> {
+    let result = scanner.scan_website(url).await?;
+
+    rank_icons(&result.favicons, target_size)
+        .into_iter()
+        .next()
+        .filter(|r| r.icon.details.is_some())
+        .ok_or_else(|| ScanError::NotFound("No reachable icon found".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::IconDetails;
+
+    fn icon(url: &str, rel: &str, sizes: Option<&str>) -> FaviconInfo {
+        FaviconInfo {
+            url: url.to_string(),
+            rel: rel.to_string(),
+            sizes: sizes.map(|s| s.to_string()),
+            mime_type: None,
+            media: None,
+            color: None,
+            purpose: None,
+            source: "html".to_string(),
+            details: None,
+        }
+    }
+
+    fn decoded(mut icon: FaviconInfo, mime_type: &str, size: Option<u32>) -> FaviconInfo {
+        icon.details = Some(IconDetails {
+            mime_type: mime_type.to_string(),
+            width: size,
+            height: size,
+            bit_depth: None,
+            file_size: 1000,
+            frames: Vec::new(),
+            size_mismatch: false,
+        });
+        icon
+    }
+
+    fn urls(ranked: &[RankedIcon]) -> Vec<&str> {
+        ranked.iter().map(|r| r.icon.url.as_str()).collect()
+    }
+
+    fn candidates() -> Vec<FaviconInfo> {
+        let mut undecoded = icon("/big.png", "icon", Some("512x512"));
+        undecoded.mime_type = Some("image/png".to_string());
+        vec![
+            undecoded,
+            decoded(icon("/favicon.ico", "icon", None), "image/x-icon", Some(48)),
+            decoded(
+                icon("/apple-touch-icon.png", "apple-touch-icon", Some("180x180")),
+                "image/png",
+                Some(180),
+            ),
+            decoded(icon("/logo.svg", "icon", None), "image/svg+xml", None),
+        ]
+    }
+
+    #[test]
+    fn svg_beats_large_png_beats_ico_beats_undecoded() {
+        let ranked = rank_icons(&candidates(), None);
+        assert_eq!(
+            urls(&ranked),
+            vec![
+                "/logo.svg",
+                "/apple-touch-icon.png",
+                "/favicon.ico",
+                "/big.png"
+            ]
+        );
+        let scores: Vec<i32> = ranked.iter().map(|r| r.score).collect();
+        assert_eq!(scores, vec![730, 410, 187, -350]);
+        assert_eq!(ranked[0].effective_size, None);
+        assert_eq!(ranked[1].effective_size, Some(180));
+        // Undecoded icons fall back to their declared size.
+        assert_eq!(ranked[3].effective_size, Some(512));
+    }
+
+    #[test]
+    fn undecoded_icons_sink_below_any_decoded_one() {
+        let mut svg = icon("/logo.svg", "icon", None);
+        svg.mime_type = Some("image/svg+xml".to_string());
+        let gif = decoded(
+            icon("/tiny.gif", "icon", Some("16x16")),
+            "image/gif",
+            Some(16),
+        );
+        let ranked = rank_icons(&[svg, gif], None);
+        assert_eq!(urls(&ranked), vec!["/tiny.gif", "/logo.svg"]);
+        assert_eq!(ranked[0].score, 80 + 12 + 30);
+        assert_eq!(ranked[1].score, 300 + 400 + 30 - 1000);
+    }
+
+    #[test]
+    fn target_size_penalizes_upscaling() {
+        let small = decoded(icon("/32.png", "icon", None), "image/png", Some(32));
+        let large = decoded(icon("/256.png", "icon", None), "image/png", Some(256));
+        let ranked = rank_icons(&[small.clone(), large.clone()], Some(32));
+        assert_eq!(urls(&ranked), vec!["/32.png", "/256.png"]);
+        let ranked = rank_icons(&[small, large], Some(192));
+        assert_eq!(urls(&ranked), vec!["/256.png", "/32.png"]);
+    }
+
+    #[test]
+    fn size_mismatch_and_purpose_cost_points() {
+        let base = decoded(icon("/a.png", "manifest", None), "image/png", Some(192));
+        let mut mismatched = base.clone();
+        mismatched.details.as_mut().unwrap().size_mismatch = true;
+        assert_eq!(score_icon(&base, None) - score_icon(&mismatched, None), 50);
+
+        let mut maskable = base.clone();
+        maskable.purpose = Some("maskable".to_string());
+        let mut monochrome = base.clone();
+        monochrome.purpose = Some("monochrome".to_string());
+        assert_eq!(score_icon(&base, None) - score_icon(&maskable, None), 150);
+        assert_eq!(score_icon(&base, None) - score_icon(&monochrome, None), 300);
+    }
+
+    #[test]
+    fn ties_keep_document_order() {
+        let first = decoded(icon("/first.png", "icon", None), "image/png", Some(64));
+        let second = decoded(icon("/second.png", "icon", None), "image/png", Some(64));
+        let ranked = rank_icons(&[first.clone(), second.clone()], None);
+        assert_eq!(urls(&ranked), vec!["/first.png", "/second.png"]);
+        let ranked = rank_icons(&[second, first], None);
+        assert_eq!(urls(&ranked), vec!["/second.png", "/first.png"]);
+    }
+}
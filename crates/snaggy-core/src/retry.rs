@@ -0,0 +1,241 @@
+use std::time::{Duration, SystemTime};
+
+use tokio::time::Instant;
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, Response, StatusCode};
+
+use crate::config::Config;
+use crate::credentials;
+use crate::egress::egress_error;
+use crate::error::Cause;
+
+/// Statuses worth another attempt: the server is busy or a gateway failed.
+const RETRY_STATUSES: [StatusCode; 5] = [
+    StatusCode::REQUEST_TIMEOUT,
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+
+/// Send a request, retrying timeouts, dropped connections and busy
+/// responses up to `max_retries` times. Only idempotent requests are
+/// retried, and not once the next wait would end more than
+/// `timeout_global` after the first attempt. Returns the final outcome and
+/// the number of retries made.
+pub(crate) async fn send(
+    cfg: &Config,
+    request: RequestBuilder,
+) -> (Result<Response, reqwest::Error>, u32) {
+    credentials::scoped(cfg, send_with_retries(cfg, request)).await
+}
+
+async fn send_with_retries(
+    cfg: &Config,
+    request: RequestBuilder,
+) -> (Result<Response, reqwest::Error>, u32) {
+    let idempotent = request
+        .try_clone()
+        .and_then(|r| r.build().ok())
+        .is_some_and(|r| r.method().is_idempotent());
+
+    let deadline = Instant::now() + cfg.timeout_global;
+    let mut retries = 0;
+    loop {
+        let attempt = match request.try_clone() {
+            Some(attempt) if idempotent && retries < cfg.max_retries => attempt,
+            _ => return (request.send().await, retries),
+        };
+        let result = attempt.send().await;
+        match retry_delay(cfg, retries, &result) {
+            Some(delay) if Instant::now() + delay <= deadline => {
+                tokio::time::sleep(delay).await;
+                retries += 1;
+            }
+            _ => return (result, retries),
+        }
+    }
+}
+
+/// How long to wait before retrying, or `None` if the outcome is final.
+fn retry_delay(
+    cfg: &Config,
+    retries: u32,
+    result: &Result<Response, reqwest::Error>,
+) -> Option<Duration> {
+    match result {
+        Ok(response) if RETRY_STATUSES.contains(&response.status()) => {
+            match retry_after(response) {
+                // Waiting longer than the cap would stall the scan; report
+                // the response instead.
+                Some(wait) if wait > cfg.retry_max_delay => None,
+                Some(wait) => Some(wait),
+                None => Some(backoff(cfg, retries)),
+            }
+        }
+        Ok(_) => None,
+        Err(e) if is_transient(e) => Some(backoff(cfg, retries)),
+        Err(_) => None,
+    }
+}
+
+/// Timeouts and refused or dropped connections. DNS, TLS, egress and
+/// unrecognized failures won't fix themselves.
+fn is_transient(err: &reqwest::Error) -> bool {
+    egress_error(err).is_none() && matches!(Cause::of(err), Cause::Timeout | Cause::Dropped)
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, SystemTime::now())
+}
+
+/// `Retry-After` as either delay seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+/// Exponential backoff with jitter: half the doubled delay plus a random
+/// share of the other half, capped at `retry_max_delay`.
+fn backoff(cfg: &Config, retries: u32) -> Duration {
+    let delay = cfg
+        .retry_base_delay
+        .saturating_mul(2u32.saturating_pow(retries))
+        .min(cfg.retry_max_delay);
+    let half = delay / 2;
+    half + half.mul_f64(fastrand::f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        let now = SystemTime::now();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("-5", now), None);
+        assert_eq!(parse_retry_after("1.5", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn parses_retry_after_dates() {
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        // Obsolete RFC 850 and asctime forms are accepted too.
+        assert_eq!(
+            parse_retry_after("Wednesday, 21-Oct-15 07:29:00 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse_retry_after("Wed Oct 21 07:28:05 2015", now),
+            Some(Duration::from_secs(5))
+        );
+        // Dates in the past mean "now".
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 32 Oct 2015 07:28:00 GMT", now),
+            None
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_within_the_cap() {
+        let cfg = Config {
+            retry_base_delay: Duration::from_millis(100),
+            retry_max_delay: Duration::from_millis(300),
+            ..Config::default()
+        };
+        for _ in 0..50 {
+            let first = backoff(&cfg, 0);
+            assert!((Duration::from_millis(50)..=Duration::from_millis(100)).contains(&first));
+            let second = backoff(&cfg, 1);
+            assert!((Duration::from_millis(100)..=Duration::from_millis(200)).contains(&second));
+            let capped = backoff(&cfg, 30);
+            assert!((Duration::from_millis(150)..=Duration::from_millis(300)).contains(&capped));
+        }
+    }
+
+    /// Answers every request with 503 and counts them.
+    async fn busy_server() -> (String, Arc<AtomicU32>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicU32::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 4096];
+                let _ = socket.read(&mut buf).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = socket
+                    .write_all(b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+        (url, hits)
+    }
+
+    #[test]
+    fn retries_stop_at_the_deadline() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (url, hits) = busy_server().await;
+            let send_with = |cfg: Config| {
+                let url = url.clone();
+                async move {
+                    let scanner = Scanner::new(cfg).unwrap();
+                    send(scanner.config(), scanner.client().get(&url)).await
+                }
+            };
+            let cfg = |timeout_global| Config {
+                allow_private_networks: true,
+                max_retries: 2,
+                retry_base_delay: Duration::from_millis(10),
+                retry_max_delay: Duration::from_millis(20),
+                timeout_global,
+                ..Config::default()
+            };
+
+            let (result, retries) = send_with(cfg(Duration::from_secs(10))).await;
+            assert_eq!(result.unwrap().status(), 503);
+            assert_eq!(retries, 2);
+
+            // The first wait would already end past the deadline.
+            let (result, retries) = send_with(Config {
+                retry_base_delay: Duration::from_secs(5),
+                retry_max_delay: Duration::from_secs(5),
+                ..cfg(Duration::from_secs(1))
+            })
+            .await;
+            assert_eq!(result.unwrap().status(), 503);
+            assert_eq!(retries, 0);
+            assert_eq!(hits.load(Ordering::SeqCst), 4);
+        });
+    }
+}
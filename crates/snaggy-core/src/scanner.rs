@@ -1,17 +1,90 @@
 use base64::Engine;
+use futures::StreamExt;
 use regex::Regex;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
 use url::Url;
 
-use crate::config::config;
+use crate::browserconfig::{TileInfo, fetch_browserconfig, tile_logo_size};
+use crate::config::Config;
+use crate::credentials;
+use crate::decode::{IconDetails, inspect_favicons};
+use crate::diagnostics::{Diagnostic, Trace};
+use crate::download::{AssetDownload, ProgressCallback, open_asset, read_limited};
+use crate::egress::{PolicyResolver, check_target, redirect_policy};
+use crate::error::ScanError;
+use crate::logo::{LogoCandidate, detect_logos};
+use crate::manifest::{ManifestInfo, fetch_manifest};
+use crate::options::{Extractor, ScanOptions};
+use crate::palette::{Palette, PaletteBuilder, media_theme};
+use crate::probe::probe_well_known_icons;
+use crate::retry;
+use crate::social::{SocialImage, extract_social_images};
+use crate::svg::RenderOptions;
+use crate::transport;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanResult {
     pub url: String,
     pub favicons: Vec<FaviconInfo>,
     pub fonts: Vec<FontInfo>,
+    pub manifest: Option<ManifestInfo>,
+    pub tile: Option<TileInfo>,
+    /// `<meta name="theme-color">` values, including media-specific variants.
+    pub theme_colors: Vec<ThemeColor>,
+    /// Open Graph, Twitter card, microdata and JSON-LD images.
+    pub images: Vec<SocialImage>,
+    /// Ranked on-page logo candidates.
+    pub logos: Vec<LogoCandidate>,
+    /// Brand colors and CSS custom properties.
+    pub palette: Palette,
+    /// Non-fatal problems, e.g. responses cut off at a size limit.
+    pub warnings: Vec<ScanWarning>,
+    /// Every request made during the scan, including failed ones.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A non-fatal problem encountered while scanning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanWarning {
+    /// "truncated" when only the first `limit` bytes were used, "too-large"
+    /// when the resource was skipped.
+    pub kind: String,
+    /// "html", "css", "manifest", "browserconfig" or "image".
+    pub resource: String,
+    pub url: String,
+    /// The size limit, in bytes, that was exceeded.
+    pub limit: u64,
+}
+
+impl ScanWarning {
+    pub(crate) fn truncated(resource: &str, url: &str, limit: u64) -> Self {
+        Self {
+            kind: "truncated".to_string(),
+            resource: resource.to_string(),
+            url: url.to_string(),
+            limit,
+        }
+    }
+
+    pub(crate) fn too_large(resource: &str, url: &str, limit: u64) -> Self {
+        Self {
+            kind: "too-large".to_string(),
+            resource: resource.to_string(),
+            url: url.to_string(),
+            limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeColor {
+    pub color: String,
+    /// Media query the color applies to, e.g. "(prefers-color-scheme: dark)".
+    pub media: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +93,16 @@ pub struct FaviconInfo {
     pub rel: String,
     pub sizes: Option<String>,
     pub mime_type: Option<String>,
+    /// Media query from the `media` attribute, e.g. for dark-mode favicons.
+    pub media: Option<String>,
+    /// Silhouette color from the `color` attribute on `rel="mask-icon"`.
+    pub color: Option<String>,
+    /// Manifest icon purpose, e.g. "any", "maskable", "monochrome".
+    pub purpose: Option<String>,
+    /// Where the icon was discovered: "html", "manifest", "browserconfig" or "well-known".
+    pub source: String,
+    /// Decoded file contents; `None` if the icon couldn't be fetched or read.
+    pub details: Option<IconDetails>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,26 +118,99 @@ pub struct FontVariant {
     pub weight: String,
     pub url: String,
     pub format: String,
+    /// Original `@font-face` descriptors other than `font-family` and `src`,
+    /// e.g. `unicode-range` or `font-display`.
+    pub descriptors: BTreeMap<String, String>,
+}
+
+/// Runs scans and downloads with one [`Config`] and one HTTP client.
+///
+/// Cheap to clone; clones share the configuration and the client's
+/// connection pool, so create one scanner per process and pass it around.
+/// Use [`Scanner::with_options`] for per-call overrides.
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    config: Arc<Config>,
+    client: reqwest::Client,
+    extractors: Vec<Extractor>,
+}
+
+impl Scanner {
+    pub fn new(config: Config) -> Result<Self, ScanError> {
+        let config = Arc::new(config);
+        Ok(Self {
+            client: build_client(&config)?,
+            config,
+            extractors: Extractor::ALL.to_vec(),
+        })
+    }
+
+    /// A scanner configured from `SNAGGY_*` environment variables.
+    pub fn from_env() -> Result<Self, ScanError> {
+        Self::new(Config::from_env()?)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// A scanner with `options` applied on top of this one's configuration.
+    ///
+    /// The client is shared: the user agent and credentials are added per
+    /// request.
+    pub fn with_options(&self, options: &ScanOptions) -> Result<Self, ScanError> {
+        let mut config = self.config.as_ref().clone();
+        options.apply(&mut config)?;
+        Ok(Self {
+            config: Arc::new(config),
+            client: self.client.clone(),
+            extractors: options
+                .extractors
+                .clone()
+                .unwrap_or_else(|| self.extractors.clone()),
+        })
+    }
+
+    /// Whether scans run the given extractor.
+    pub fn runs(&self, extractor: Extractor) -> bool {
+        self.extractors.contains(&extractor)
+    }
+
+    pub(crate) fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
 }
 
-pub fn build_client() -> Result<reqwest::Client, String> {
-    let cfg = config();
-    reqwest::Client::builder()
+/// Build the HTTP client. Every request it makes, including each redirect
+/// hop, is subject to the egress policy and goes through the configured
+/// proxy. Responses are decompressed transparently (gzip, Brotli, zstd,
+/// deflate).
+fn build_client(cfg: &Arc<Config>) -> Result<reqwest::Client, ScanError> {
+    let mut builder = reqwest::Client::builder()
         .user_agent(&cfg.user_agent)
-        .redirect(reqwest::redirect::Policy::limited(cfg.max_redirects))
+        .redirect(redirect_policy(cfg.clone()))
+        .dns_resolver(Arc::new(PolicyResolver {
+            config: cfg.clone(),
+        }))
         .timeout(cfg.timeout_global)
+        .pool_max_idle_per_host(cfg.pool_max_idle_per_host)
+        .pool_idle_timeout(cfg.pool_idle_timeout);
+    if !cfg.http2 {
+        builder = builder.http1_only();
+    }
+    transport::configure(builder, cfg)?
         .build()
-        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+        .map_err(|e| ScanError::Internal(format!("Failed to build HTTP client: {}", e)))
 }
 
-pub fn normalize_url(input: &str) -> Result<Url, String> {
+pub fn normalize_url(input: &str) -> Result<Url, ScanError> {
     let trimmed = input.trim();
     let with_scheme = if !trimmed.contains("://") {
         format!("https://{}", trimmed)
     } else {
         trimmed.to_string()
     };
-    Url::parse(&with_scheme).map_err(|e| format!("Invalid URL: {}", e))
+    Url::parse(&with_scheme).map_err(|e| ScanError::InvalidUrl(e.to_string()))
 }
 
 fn resolve_url(base: &Url, href: &str) -> Option<String> {
@@ -96,6 +252,23 @@ struct RawFontFace {
     style: String,
     url: String,
     format: String,
+    descriptors: BTreeMap<String, String>,
+}
+
+/// Collect a `@font-face` block's descriptors, minus `font-family` and `src`.
+fn parse_descriptors(block: &str) -> BTreeMap<String, String> {
+    block
+        .split(';')
+        .filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            let name = name.trim().to_lowercase();
+            let value = value.trim();
+            let valid =
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '-');
+            (valid && name != "src" && name != "font-family" && !value.is_empty())
+                .then(|| (name, value.to_string()))
+        })
+        .collect()
 }
 
 fn parse_font_faces(css: &str, stylesheet_url: &Url) -> Vec<RawFontFace> {
@@ -170,6 +343,7 @@ fn parse_font_faces(css: &str, stylesheet_url: &Url) -> Vec<RawFontFace> {
                 style,
                 url,
                 format,
+                descriptors: parse_descriptors(block),
             });
         }
     }
@@ -192,6 +366,7 @@ fn group_fonts(raw_faces: Vec<RawFontFace>) -> Vec<FontInfo> {
                 weight: face.weight,
                 url: face.url,
                 format: face.format,
+                descriptors: face.descriptors,
             });
     }
 
@@ -225,8 +400,14 @@ fn group_fonts(raw_faces: Vec<RawFontFace>) -> Vec<FontInfo> {
 
 struct ParsedPage {
     favicons: Vec<FaviconInfo>,
-    favicon_ico_url: Option<String>,
+    manifest_url: Option<String>,
+    browserconfig_url: Option<String>,
+    tile_color: Option<String>,
+    theme_colors: Vec<ThemeColor>,
+    images: Vec<SocialImage>,
+    logos: Vec<LogoCandidate>,
     inline_faces: Vec<RawFontFace>,
+    palette: PaletteBuilder,
     css_urls: Vec<String>,
 }
 
@@ -264,275 +445,532 @@ fn parse_html_sync(html_text: &str, base_url: &Url) -> ParsedPage {
             rel,
             sizes: element.value().attr("sizes").map(|s| s.to_string()),
             mime_type: element.value().attr("type").map(|t| t.to_string()),
+            media: element.value().attr("media").map(|m| m.trim().to_string()),
+            color: element.value().attr("color").map(|c| c.trim().to_string()),
+            purpose: None,
+            source: "html".to_string(),
+            details: None,
         });
     }
 
-    let favicon_ico = format!(
-        "{}://{}/favicon.ico",
-        base_url.scheme(),
-        base_url.host_str().unwrap_or_default()
-    );
-    let favicon_ico_url = if seen_urls.contains(&favicon_ico) {
-        None
-    } else {
-        Some(favicon_ico)
-    };
+    let manifest_selector = Selector::parse("link[rel~='manifest'][href]").unwrap();
+    let manifest_url = document
+        .select(&manifest_selector)
+        .filter_map(|e| e.value().attr("href"))
+        .find_map(|href| resolve_url(base_url, href));
+
+    // Windows tiles: msapplication-* metas, falling back to /browserconfig.xml
+    // unless the page opts out with msapplication-config="none".
+    let mut tile_color = None;
+    let mut browserconfig_url = resolve_url(base_url, "/browserconfig.xml");
+    let meta_selector = Selector::parse("meta[name][content]").unwrap();
+    for element in document.select(&meta_selector) {
+        let name = element.value().attr("name").unwrap_or_default();
+        let content = element.value().attr("content").unwrap_or_default().trim();
+        let lower = name.to_lowercase();
+        let suffix = match lower.strip_prefix("msapplication-") {
+            Some(s) => s,
+            None => continue,
+        };
+        if content.is_empty() {
+            continue;
+        }
+
+        match suffix {
+            "tilecolor" => tile_color = Some(content.to_string()),
+            "config" => {
+                browserconfig_url = if content.eq_ignore_ascii_case("none") {
+                    None
+                } else {
+                    resolve_url(base_url, content)
+                };
+            }
+            _ => {
+                let size = match tile_logo_size(suffix) {
+                    Some(s) => s,
+                    None => continue,
+                };
+                let abs_url = match resolve_url(base_url, content) {
+                    Some(u) => u,
+                    None => continue,
+                };
+                if !seen_urls.insert(abs_url.clone()) {
+                    continue;
+                }
+                favicons.push(FaviconInfo {
+                    url: abs_url,
+                    rel: name.to_string(),
+                    sizes: Some(size.to_string()),
+                    mime_type: None,
+                    media: None,
+                    color: None,
+                    purpose: None,
+                    source: "html".to_string(),
+                    details: None,
+                });
+            }
+        }
+    }
+
+    let theme_selector = Selector::parse("meta[name][content]").unwrap();
+    let theme_colors = document
+        .select(&theme_selector)
+        .filter(|e| {
+            e.value()
+                .attr("name")
+                .is_some_and(|n| n.eq_ignore_ascii_case("theme-color"))
+        })
+        .filter_map(|e| {
+            let color = e.value().attr("content")?.trim();
+            (!color.is_empty()).then(|| ThemeColor {
+                color: color.to_string(),
+                media: e.value().attr("media").map(|m| m.trim().to_string()),
+            })
+        })
+        .collect();
+
+    let images = extract_social_images(&document, base_url);
+    let logos = detect_logos(&document, base_url);
 
     let mut inline_faces = Vec::new();
+    let mut palette = PaletteBuilder::new();
 
     let style_selector = Selector::parse("style").unwrap();
     for element in document.select(&style_selector) {
         let css_text = element.text().collect::<String>();
         inline_faces.extend(parse_font_faces(&css_text, base_url));
+        palette.add_css(&css_text);
+    }
+
+    let style_attr_selector = Selector::parse("[style]").unwrap();
+    for element in document.select(&style_attr_selector) {
+        if let Some(style) = element.value().attr("style") {
+            palette.add_declarations(style, "default");
+        }
     }
 
     let stylesheet_selector =
         Selector::parse("link[rel='stylesheet'], link[rel='preload'][as='style']").unwrap();
     let mut css_urls = Vec::new();
     for element in document.select(&stylesheet_selector) {
-        if let Some(href) = element.value().attr("href") {
-            if let Some(abs_url) = resolve_url(base_url, href) {
-                css_urls.push(abs_url);
-            }
+        if let Some(href) = element.value().attr("href")
+            && let Some(abs_url) = resolve_url(base_url, href)
+        {
+            css_urls.push(abs_url);
         }
     }
-    css_urls.truncate(config().max_stylesheets);
 
     ParsedPage {
         favicons,
-        favicon_ico_url,
+        manifest_url,
+        browserconfig_url,
+        tile_color,
+        theme_colors,
+        images,
+        logos,
         inline_faces,
+        palette,
         css_urls,
     }
 }
 
+/// Fetch a stylesheet, truncating it at `max_css_size`.
+async fn fetch_stylesheet(
+    cfg: &Config,
+    url: &str,
+    client: &reqwest::Client,
+    warnings: &mut Vec<ScanWarning>,
+    trace: &mut Trace,
+) -> Option<String> {
+    if !trace.allowed(cfg).await {
+        return None;
+    }
+    let resp = trace
+        .send(
+            cfg,
+            credentials::get(cfg, client, url).timeout(cfg.timeout_request),
+        )
+        .await?;
+
+    let body = trace.read(resp, cfg.max_css_size).await?;
+    if body.truncated {
+        warnings.push(ScanWarning::truncated("css", url, cfg.max_css_size));
+    }
+    trace.parsed(body.bytes.len());
+    Some(String::from_utf8_lossy(&body.bytes).into_owned())
+}
+
+/// Fetch one stylesheet and collect its font faces and colors, recording a
+/// diagnostic. Returns the CSS text so its `@import`s can be followed.
+async fn load_stylesheet(
+    cfg: &Config,
+    css_url: &str,
+    client: &reqwest::Client,
+    faces: &mut Vec<RawFontFace>,
+    palette: &mut PaletteBuilder,
+    warnings: &mut Vec<ScanWarning>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<(String, Url)> {
+    let mut trace = Trace::new("css", css_url);
+    let parsed_url = match Url::parse(css_url) {
+        Ok(u) => u,
+        Err(e) => {
+            trace.fail(ScanError::InvalidUrl(e.to_string()));
+            diagnostics.push(trace.finish());
+            return None;
+        }
+    };
+
+    let css_text = fetch_stylesheet(cfg, css_url, client, warnings, &mut trace).await;
+    if let Some(text) = &css_text {
+        let found = parse_font_faces(text, &parsed_url);
+        trace.faces_found(found.len());
+        faces.extend(found);
+        palette.add_css(text);
+    }
+    diagnostics.push(trace.finish());
+    Some((css_text?, parsed_url))
+}
+
 async fn fetch_and_parse_stylesheets(
+    cfg: &Config,
     css_urls: &[String],
     client: &reqwest::Client,
+    palette: &mut PaletteBuilder,
+    warnings: &mut Vec<ScanWarning>,
+    diagnostics: &mut Vec<Diagnostic>,
 ) -> Vec<RawFontFace> {
-    let cfg = config();
     let mut all_faces = Vec::new();
+    let import_re =
+        Regex::new(r#"(?i)@import\s+(?:url\(\s*['"]?([^'")]+?)['"]?\s*\)|['"]([^'"]+?)['"])"#)
+            .unwrap();
 
     for css_url in css_urls {
-        let parsed_url = match Url::parse(css_url) {
-            Ok(u) => u,
-            Err(_) => continue,
-        };
-
-        let resp = match client
-            .get(css_url)
-            .timeout(cfg.timeout_request)
-            .send()
-            .await
-        {
-            Ok(r) => r,
-            Err(_) => continue,
-        };
-
-        if !resp.status().is_success() {
+        let Some((css_text, parsed_url)) = load_stylesheet(
+            cfg,
+            css_url,
+            client,
+            &mut all_faces,
+            palette,
+            warnings,
+            diagnostics,
+        )
+        .await
+        else {
             continue;
-        }
-
-        let css_text = match resp.text().await {
-            Ok(t) => t,
-            Err(_) => continue,
         };
 
-        let faces = parse_font_faces(&css_text, &parsed_url);
-        all_faces.extend(faces);
-
-        let import_re =
-            Regex::new(r#"(?i)@import\s+(?:url\(\s*['"]?([^'")]+?)['"]?\s*\)|['"]([^'"]+?)['"])"#)
-                .unwrap();
         let mut import_urls = Vec::new();
         for cap in import_re.captures_iter(&css_text) {
             let import_href = cap.get(1).or(cap.get(2)).map(|m| m.as_str());
-            if let Some(href) = import_href {
-                if let Some(abs) = resolve_url(&parsed_url, href) {
-                    import_urls.push(abs);
-                }
+            if let Some(href) = import_href
+                && let Some(abs) = resolve_url(&parsed_url, href)
+            {
+                import_urls.push(abs);
             }
         }
 
         import_urls.truncate(cfg.max_imports);
         for import_url in &import_urls {
-            let import_parsed = match Url::parse(import_url) {
-                Ok(u) => u,
-                Err(_) => continue,
-            };
-
-            let resp = match client
-                .get(import_url)
-                .timeout(cfg.timeout_request)
-                .send()
-                .await
-            {
-                Ok(r) => r,
-                Err(_) => continue,
-            };
-
-            if resp.status().is_success() {
-                if let Ok(text) = resp.text().await {
-                    let faces = parse_font_faces(&text, &import_parsed);
-                    all_faces.extend(faces);
-                }
-            }
+            load_stylesheet(
+                cfg,
+                import_url,
+                client,
+                &mut all_faces,
+                palette,
+                warnings,
+                diagnostics,
+            )
+            .await;
         }
     }
 
     all_faces
 }
 
-async fn check_favicon_ico(favicon_ico_url: &str, client: &reqwest::Client) -> Option<FaviconInfo> {
-    let resp = client
-        .head(favicon_ico_url)
-        .timeout(config().timeout_probe)
-        .send()
-        .await
-        .ok()?;
-
-    if !resp.status().is_success() {
-        return None;
-    }
-
-    let ct = resp
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or_default()
-        .to_string();
-
-    if ct.contains("image") || ct.contains("icon") || ct.is_empty() {
-        Some(FaviconInfo {
-            url: favicon_ico_url.to_string(),
-            rel: "icon".to_string(),
-            sizes: None,
-            mime_type: if ct.is_empty() {
-                Some("image/x-icon".to_string())
-            } else {
-                Some(ct)
-            },
-        })
-    } else {
-        None
+/// Append icons not already present (by URL).
+fn merge_favicons(favicons: &mut Vec<FaviconInfo>, extra: Vec<FaviconInfo>) {
+    for icon in extra {
+        if !favicons.iter().any(|f| f.url == icon.url) {
+            favicons.push(icon);
+        }
     }
 }
 
-/// Scan a website and return all discovered favicons and fonts.
-pub async fn scan_website(url: &str) -> Result<ScanResult, String> {
-    let base_url = normalize_url(url)?;
-    let client = build_client()?;
-
-    let response = client
-        .get(base_url.as_str())
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch website: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!(
-            "Website returned status {}",
-            response.status().as_u16()
-        ));
-    }
-
-    let html_text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-
-    let base_url_clone = base_url.clone();
-    let parsed = tokio::task::spawn_blocking(move || parse_html_sync(&html_text, &base_url_clone))
-        .await
-        .map_err(|e| format!("Parse task failed: {}", e))?;
-
-    let mut favicons = parsed.favicons;
-
-    if let Some(ico_url) = &parsed.favicon_ico_url {
-        if let Some(ico) = check_favicon_ico(ico_url, &client).await {
-            favicons.push(ico);
+impl Scanner {
+    /// Scan a website and return all discovered favicons and fonts.
+    pub async fn scan_website(&self, url: &str) -> Result<ScanResult, ScanError> {
+        let cfg = &*self.config;
+        let base_url = normalize_url(url)?;
+        check_target(cfg, &base_url).await?;
+        let client = self.client();
+
+        let mut trace = Trace::new("html", base_url.as_str());
+        let (result, retries) = retry::send(
+            cfg,
+            credentials::get(cfg, client, base_url.as_str()).timeout(cfg.timeout_request),
+        )
+        .await;
+        trace.retried(retries);
+        let response = result.map_err(ScanError::from_request)?;
+        trace.response(&response);
+
+        if !response.status().is_success() {
+            return Err(ScanError::upstream_status(
+                base_url.as_str(),
+                response.status(),
+            ));
         }
-    }
 
-    let mut all_faces = parsed.inline_faces;
-    let stylesheet_faces = fetch_and_parse_stylesheets(&parsed.css_urls, &client).await;
-    all_faces.extend(stylesheet_faces);
-
-    let fonts = group_fonts(all_faces);
+        let max_html_size = cfg.max_html_size;
+        let body = read_limited(response, max_html_size).await?;
+        trace.received(body.bytes.len());
+        trace.parsed(body.bytes.len());
+        let mut diagnostics = vec![trace.finish()];
+        let mut warnings = Vec::new();
+        if body.truncated {
+            warnings.push(ScanWarning::truncated(
+                "html",
+                base_url.as_str(),
+                max_html_size,
+            ));
+        }
+        let html_text = String::from_utf8_lossy(&body.bytes).into_owned();
 
-    Ok(ScanResult {
-        url: base_url.to_string(),
-        favicons,
-        fonts,
-    })
-}
+        let base_url_clone = base_url.clone();
+        let mut parsed =
+            tokio::task::spawn_blocking(move || parse_html_sync(&html_text, &base_url_clone))
+                .await
+                .map_err(|e| ScanError::Internal(format!("Parse task failed: {}", e)))?;
+        parsed.css_urls.truncate(cfg.max_stylesheets);
 
-/// Proxy an image URL and return its data as a base64 data URI.
-pub async fn proxy_image(url: &str) -> Result<String, String> {
-    let client = build_client()?;
+        let mut favicons = parsed.favicons;
 
-    let response = client
-        .get(url)
-        .timeout(config().timeout_image)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch image: {}", e))?;
+        let mut manifest = None;
+        if self.runs(Extractor::Manifest)
+            && let Some(manifest_url) = &parsed.manifest_url
+            && let Some((info, icons)) =
+                fetch_manifest(cfg, manifest_url, client, &mut warnings, &mut diagnostics).await
+        {
+            merge_favicons(&mut favicons, icons);
+            manifest = Some(info);
+        }
 
-    let content_type = response
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("image/png")
-        .to_string();
+        let mut tile = parsed.tile_color.clone().map(|c| TileInfo {
+            config_url: None,
+            tile_color: Some(c),
+        });
+        if self.runs(Extractor::Browserconfig)
+            && let Some(config_url) = &parsed.browserconfig_url
+            && let Some((tile_color, logos)) =
+                fetch_browserconfig(cfg, config_url, client, &mut warnings, &mut diagnostics).await
+        {
+            merge_favicons(&mut favicons, logos);
+            let info = tile.get_or_insert_with(TileInfo::default);
+            info.config_url = Some(config_url.clone());
+            if info.tile_color.is_none() {
+                info.tile_color = tile_color;
+            }
+        }
 
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read image: {}", e))?;
+        if self.runs(Extractor::WellKnownIcons) {
+            let (probed, probe_diagnostics) =
+                probe_well_known_icons(cfg, &base_url, &favicons, client).await;
+            merge_favicons(&mut favicons, probed);
+            diagnostics.extend(probe_diagnostics);
+        }
+        if self.runs(Extractor::IconDetails) {
+            let (icon_warnings, icon_diagnostics) =
+                inspect_favicons(cfg, &mut favicons, client).await;
+            warnings.extend(icon_warnings);
+            diagnostics.extend(icon_diagnostics);
+        }
 
-    let b64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
-    Ok(format!("data:{};base64,{}", content_type, b64))
-}
+        let mut palette = parsed.palette;
+        let mut all_faces = parsed.inline_faces;
+        if self.runs(Extractor::Stylesheets) {
+            let stylesheet_faces = fetch_and_parse_stylesheets(
+                cfg,
+                &parsed.css_urls,
+                client,
+                &mut palette,
+                &mut warnings,
+                &mut diagnostics,
+            )
+            .await;
+            all_faces.extend(stylesheet_faces);
+        }
 
-/// Download an asset and return its bytes.
-pub async fn download_asset_bytes(url: &str) -> Result<(Vec<u8>, String), String> {
-    let client = build_client()?;
+        let fonts = group_fonts(all_faces);
 
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| format!("Download failed: {}", e))?;
+        for theme_color in &parsed.theme_colors {
+            let theme = theme_color
+                .media
+                .as_deref()
+                .and_then(media_theme)
+                .unwrap_or("default");
+            palette.add_declared(&theme_color.color, "theme-color", theme);
+        }
+        if let Some(info) = &manifest {
+            for color in [&info.theme_color, &info.background_color]
+                .into_iter()
+                .flatten()
+            {
+                palette.add_declared(color, "manifest", "default");
+            }
+        }
+        if let Some(color) = tile.as_ref().and_then(|t| t.tile_color.as_ref()) {
+            palette.add_declared(color, "tile", "default");
+        }
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "Download returned status {}",
-            response.status().as_u16()
-        ));
+        Ok(ScanResult {
+            url: base_url.to_string(),
+            favicons,
+            fonts,
+            manifest,
+            tile,
+            theme_colors: parsed.theme_colors,
+            images: if self.runs(Extractor::Images) {
+                parsed.images
+            } else {
+                Vec::new()
+            },
+            logos: if self.runs(Extractor::Logos) {
+                parsed.logos
+            } else {
+                Vec::new()
+            },
+            palette: palette.build(),
+            warnings,
+            diagnostics,
+        })
     }
 
-    let content_type = response
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("application/octet-stream")
-        .to_string();
+    /// Proxy an image URL and return its data as a base64 data URI.
+    ///
+    /// SVGs are sanitized, or rasterized to PNG when `options` requests a size
+    /// or color scheme.
+    pub async fn proxy_image(
+        &self,
+        url: &str,
+        options: &RenderOptions,
+    ) -> Result<String, ScanError> {
+        let cfg = &*self.config;
+        let client = self.client();
+        let request = credentials::get(cfg, client, url).timeout(cfg.timeout_image);
+
+        let download = open_asset(cfg, request, url, options, cfg.max_image_size, None).await?;
+        let content_type = download.content_type.clone();
+        let bytes = download.bytes().await?;
+
+        let b64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
+        Ok(format!("data:{};base64,{}", content_type, b64))
+    }
 
-    let bytes = response
-        .bytes()
+    /// Start downloading an asset, streaming its body as it arrives.
+    ///
+    /// The body is limited to `max_download_size`; `progress` is called after
+    /// every chunk. SVGs are sanitized, or rasterized to PNG when `options`
+    /// requests it.
+    pub async fn open_asset_download(
+        &self,
+        url: &str,
+        options: &RenderOptions,
+        progress: Option<ProgressCallback>,
+    ) -> Result<AssetDownload, ScanError> {
+        let client = self.client();
+        open_asset(
+            &self.config,
+            credentials::get(&self.config, client, url),
+            url,
+            options,
+            self.config.max_download_size,
+            progress,
+        )
         .await
-        .map_err(|e| format!("Failed to read download: {}", e))?;
+    }
 
-    Ok((bytes.to_vec(), content_type))
-}
+    /// Download an asset and return its bytes and content type.
+    ///
+    /// SVGs are sanitized, or rasterized to PNG when `options` requests it.
+    pub async fn download_asset_bytes(
+        &self,
+        url: &str,
+        options: &RenderOptions,
+    ) -> Result<(Vec<u8>, String), ScanError> {
+        self.fetch_asset_bytes(url, options, self.config.max_download_size)
+            .await
+    }
 
-/// Download an asset and save to a file path.
-pub async fn download_asset(url: &str, save_path: &str) -> Result<(), String> {
-    let (bytes, _) = download_asset_bytes(url).await?;
+    /// Like [`download_asset_bytes`], with a caller-chosen size limit (e.g.
+    /// `max_font_size` for fonts).
+    pub(crate) async fn fetch_asset_bytes(
+        &self,
+        url: &str,
+        options: &RenderOptions,
+        max_size: u64,
+    ) -> Result<(Vec<u8>, String), ScanError> {
+        let client = self.client();
+        let download = open_asset(
+            &self.config,
+            credentials::get(&self.config, client, url),
+            url,
+            options,
+            max_size,
+            None,
+        )
+        .await?;
+        let content_type = download.content_type.clone();
+        Ok((download.bytes().await?, content_type))
+    }
 
-    tokio::fs::write(save_path, &bytes)
-        .await
-        .map_err(|e| format!("Failed to write file: {}", e))?;
+    /// Download an asset and stream it to a file path.
+    ///
+    /// The data is written to a `.part` file that replaces `save_path` only once
+    /// the download completes.
+    pub async fn download_asset(
+        &self,
+        url: &str,
+        save_path: &str,
+        options: &RenderOptions,
+        progress: Option<ProgressCallback>,
+    ) -> Result<(), ScanError> {
+        let mut download = self.open_asset_download(url, options, progress).await?;
+
+        let part_path = format!("{}.part", save_path);
+        let mut file = tokio::fs::File::create(&part_path)
+            .await
+            .map_err(|e| ScanError::Io(format!("Failed to create file: {}", e)))?;
+
+        let mut result = Ok(());
+        while let Some(chunk) = download.body.next().await {
+            result = match chunk {
+                Ok(chunk) => file
+                    .write_all(&chunk)
+                    .await
+                    .map_err(|e| ScanError::Io(format!("Failed to write file: {}", e))),
+                Err(e) => Err(e),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() {
+            result = file
+                .flush()
+                .await
+                .map_err(|e| ScanError::Io(format!("Failed to write file: {}", e)));
+        }
+        drop(file);
 
-    Ok(())
+        match result {
+            Ok(()) => tokio::fs::rename(&part_path, save_path)
+                .await
+                .map_err(|e| ScanError::Io(format!("Failed to write file: {}", e))),
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&part_path).await;
+                Err(e)
+            }
+        }
+    }
 }
@@ -0,0 +1,319 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+/// A share or brand image a site publishes for social cards and search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SocialImage {
+    pub url: String,
+    /// Where it was declared: "og:image", "twitter:image", "og:logo",
+    /// "itemprop-logo" or "json-ld-logo".
+    pub kind: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub alt: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+impl SocialImage {
+    fn new(url: String, kind: &str) -> Self {
+        Self {
+            url,
+            kind: kind.to_string(),
+            width: None,
+            height: None,
+            alt: None,
+            mime_type: None,
+        }
+    }
+}
+
+fn resolve(base_url: &Url, href: &str) -> Option<String> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with("data:") {
+        return None;
+    }
+    base_url.join(href).ok().map(|u| u.to_string())
+}
+
+fn parse_dimension(value: &str) -> Option<u32> {
+    value.trim().trim_end_matches("px").parse().ok()
+}
+
+/// Schema.org types whose `logo` is the site's own brand mark.
+fn is_organization_type(value: &Value) -> bool {
+    let matches = |t: &str| t.contains("Organization") || t.contains("Corporation") || t == "Brand";
+    match value {
+        Value::String(t) => matches(t),
+        Value::Array(types) => types.iter().filter_map(Value::as_str).any(matches),
+        _ => false,
+    }
+}
+
+/// A schema.org logo is a URL string or an ImageObject with `url`/`contentUrl`.
+fn logo_urls(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => out.push(s.clone()),
+        Value::Array(items) => items.iter().for_each(|v| logo_urls(v, out)),
+        Value::Object(obj) => {
+            if let Some(url) = obj
+                .get("url")
+                .or_else(|| obj.get("contentUrl"))
+                .and_then(Value::as_str)
+            {
+                out.push(url.to_string());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Walk a JSON-LD value (including `@graph` and nested `publisher` objects)
+/// collecting organization logos.
+fn walk_json_ld(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|v| walk_json_ld(v, out)),
+        Value::Object(obj) => {
+            if obj.get("@type").is_some_and(is_organization_type)
+                && let Some(logo) = obj.get("logo")
+            {
+                logo_urls(logo, out);
+            }
+            obj.values().for_each(|v| walk_json_ld(v, out));
+        }
+        _ => {}
+    }
+}
+
+/// Organization logo URLs declared in JSON-LD `<script>` blocks, resolved.
+pub(crate) fn json_ld_logos(document: &Html, base_url: &Url) -> Vec<String> {
+    let selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    let mut raw = Vec::new();
+    for element in document.select(&selector) {
+        let text = element.text().collect::<String>();
+        if let Ok(value) = serde_json::from_str::<Value>(text.trim()) {
+            walk_json_ld(&value, &mut raw);
+        }
+    }
+
+    let mut urls: Vec<String> = Vec::new();
+    for href in raw {
+        if let Some(url) = resolve(base_url, &href)
+            && !urls.contains(&url)
+        {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+/// Extract Open Graph, Twitter card, microdata and JSON-LD images.
+pub(crate) fn extract_social_images(document: &Html, base_url: &Url) -> Vec<SocialImage> {
+    let mut images: Vec<SocialImage> = Vec::new();
+    // Structured og:image:* properties describe the most recent og:image.
+    let mut current_og: Option<usize> = None;
+
+    let meta_selector = Selector::parse("meta[content]").unwrap();
+    for element in document.select(&meta_selector) {
+        let key = element
+            .value()
+            .attr("property")
+            .or_else(|| element.value().attr("name"))
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        let content = element.value().attr("content").unwrap_or_default().trim();
+
+        match key.as_str() {
+            "og:image" | "og:image:url" | "og:image:secure_url" => {
+                // og:image:url / secure_url restate the current image's URL.
+                if key != "og:image"
+                    && let Some(i) = current_og
+                {
+                    if key == "og:image:secure_url"
+                        && let Some(url) = resolve(base_url, content)
+                    {
+                        images[i].url = url;
+                    }
+                    continue;
+                }
+                if let Some(url) = resolve(base_url, content) {
+                    images.push(SocialImage::new(url, "og:image"));
+                    current_og = Some(images.len() - 1);
+                }
+            }
+            "og:image:width" => {
+                if let Some(i) = current_og {
+                    images[i].width = parse_dimension(content);
+                }
+            }
+            "og:image:height" => {
+                if let Some(i) = current_og {
+                    images[i].height = parse_dimension(content);
+                }
+            }
+            "og:image:alt" => {
+                if let Some(i) = current_og {
+                    images[i].alt = Some(content.to_string());
+                }
+            }
+            "og:image:type" => {
+                if let Some(i) = current_og {
+                    images[i].mime_type = Some(content.to_string());
+                }
+            }
+            "twitter:image" | "twitter:image:src" => {
+                if let Some(url) = resolve(base_url, content) {
+                    images.push(SocialImage::new(url, "twitter:image"));
+                }
+            }
+            "twitter:image:alt" => {
+                if let Some(img) = images.iter_mut().rev().find(|i| i.kind == "twitter:image") {
+                    img.alt = Some(content.to_string());
+                }
+            }
+            "og:logo" => {
+                if let Some(url) = resolve(base_url, content) {
+                    images.push(SocialImage::new(url, "og:logo"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Microdata: <meta itemprop="logo" content>, <img itemprop="logo" src>,
+    // <link itemprop="logo" href>.
+    let itemprop_selector = Selector::parse(r#"[itemprop~="logo"]"#).unwrap();
+    for element in document.select(&itemprop_selector) {
+        let v = element.value();
+        let href = v
+            .attr("content")
+            .or_else(|| v.attr("src"))
+            .or_else(|| v.attr("href"));
+        if let Some(url) = href.and_then(|h| resolve(base_url, h)) {
+            let mut image = SocialImage::new(url, "itemprop-logo");
+            image.alt = v.attr("alt").map(|a| a.to_string());
+            image.width = v.attr("width").and_then(parse_dimension);
+            image.height = v.attr("height").and_then(parse_dimension);
+            images.push(image);
+        }
+    }
+
+    for url in json_ld_logos(document, base_url) {
+        images.push(SocialImage::new(url, "json-ld-logo"));
+    }
+
+    let mut unique: Vec<SocialImage> = Vec::new();
+    for image in images {
+        if !unique
+            .iter()
+            .any(|u| u.url == image.url && u.kind == image.kind)
+        {
+            unique.push(image);
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(html: &str) -> Vec<SocialImage> {
+        let base = Url::parse("https://example.com/blog/post").unwrap();
+        extract_social_images(&Html::parse_document(html), &base)
+    }
+
+    #[test]
+    fn og_image_properties_describe_the_latest_image() {
+        let images = extract(
+            r#"<head>
+            <meta property="og:image" content="/share/first.png">
+            <meta property="og:image:secure_url" content="https://cdn.example.com/first.png">
+            <meta property="og:image:width" content="1200px">
+            <meta property="og:image:height" content="630">
+            <meta property="og:image:type" content="image/png">
+            <meta property="og:image:alt" content="First card">
+            <meta property="og:image" content="second.jpg">
+            <meta property="og:image:width" content="wide">
+            <meta name="twitter:image" content="/tw.png">
+            <meta name="twitter:image:alt" content="Tweet card">
+            </head>"#,
+        );
+        assert_eq!(images.len(), 3);
+
+        let first = &images[0];
+        assert_eq!(first.kind, "og:image");
+        assert_eq!(first.url, "https://cdn.example.com/first.png");
+        assert_eq!((first.width, first.height), (Some(1200), Some(630)));
+        assert_eq!(first.mime_type.as_deref(), Some("image/png"));
+        assert_eq!(first.alt.as_deref(), Some("First card"));
+
+        let second = &images[1];
+        assert_eq!(second.url, "https://example.com/blog/second.jpg");
+        assert_eq!((second.width, second.height), (None, None));
+        assert_eq!(second.alt, None);
+
+        assert_eq!(images[2].kind, "twitter:image");
+        assert_eq!(images[2].alt.as_deref(), Some("Tweet card"));
+    }
+
+    #[test]
+    fn og_image_url_alone_declares_an_image() {
+        let images = extract(
+            r#"<meta property="og:image:width" content="100">
+            <meta property="og:image:url" content="/alias.png">
+            <meta property="og:image:height" content="50">"#,
+        );
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].url, "https://example.com/alias.png");
+        assert_eq!((images[0].width, images[0].height), (None, Some(50)));
+    }
+
+    #[test]
+    fn json_ld_logo_as_string_and_image_object() {
+        let images = extract(
+            r#"<script type="application/ld+json">
+            {"@type": "Organization", "logo": "/brand/string.svg"}
+            </script>
+            <script type="application/ld+json">
+            {"@graph": [
+                {"@type": "WebSite", "logo": "/not-an-org.png"},
+                {"@type": ["Thing", "Corporation"],
+                 "logo": {"@type": "ImageObject", "url": "https://cdn.example.com/object.png"}},
+                {"@type": "Article",
+                 "publisher": {"@type": "Organization",
+                               "logo": {"@type": "ImageObject", "contentUrl": "content.png"}}}
+            ]}
+            </script>
+            <script type="application/ld+json">{ not json </script>"#,
+        );
+        let logos: Vec<&str> = images
+            .iter()
+            .filter(|i| i.kind == "json-ld-logo")
+            .map(|i| i.url.as_str())
+            .collect();
+        assert_eq!(
+            logos,
+            vec![
+                "https://example.com/brand/string.svg",
+                "https://cdn.example.com/object.png",
+                "https://example.com/blog/content.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn microdata_logos_and_duplicates() {
+        let images = extract(
+            r#"<img itemprop="logo" src="/logo.png" alt="Example" width="120" height="40">
+            <link itemprop="logo" href="/logo.png">
+            <meta property="og:logo" content="/logo.png">"#,
+        );
+        let kinds: Vec<&str> = images.iter().map(|i| i.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["og:logo", "itemprop-logo"]);
+        assert_eq!(images[1].alt.as_deref(), Some("Example"));
+        assert_eq!((images[1].width, images[1].height), (Some(120), Some(40)));
+    }
+}
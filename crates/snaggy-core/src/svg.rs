@@ -0,0 +1,473 @@
+use std::io::Cursor;
+use std::sync::LazyLock;
+
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, RgbaImage};
+use quick_xml::events::{BytesCData, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use regex::Regex;
+use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
+
+use crate::palette::media_theme;
+use crate::probe::sniff_image_type;
+
+/// Upper bound on rasterized output, whatever size the caller asks for.
+const MAX_RASTER_SIZE: u32 = 2048;
+
+/// Fallback raster size for SVGs with no usable intrinsic size.
+const DEFAULT_RASTER_SIZE: u32 = 256;
+
+/// Elements removed together with their whole subtree.
+const FORBIDDEN_ELEMENTS: &[&str] = &[
+    "script",
+    "foreignobject",
+    "iframe",
+    "embed",
+    "object",
+    "handler",
+    "listener",
+];
+
+/// Attributes whose value is a URL, by local name: any namespace prefix can
+/// be bound to XLink.
+const URL_ATTRIBUTES: &[&str] = &["href", "src"];
+
+static IMPORT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)@import[^;]*;?").unwrap());
+static CSS_URL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)url\(\s*['"]?([^'")]*)['"]?\s*\)"#).unwrap());
+static MEDIA_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)@media\s*([^{]*)\{").unwrap());
+/// A media query testing nothing but `prefers-color-scheme`, with
+/// whitespace removed.
+static SCHEME_ONLY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:not)?(?:(?:only)?(?:screen|all)and)?\(prefers-color-scheme:[a-z-]+\)$")
+        .unwrap()
+});
+
+/// Which `prefers-color-scheme` branch to apply when rasterizing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// Optional rasterization for served images. With no field set, SVGs are
+/// returned sanitized but otherwise untouched, and raster images as fetched.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RenderOptions {
+    /// Output size in pixels for rasterized SVGs (square, scaled to fit).
+    pub size: Option<u32>,
+    pub color_scheme: Option<ColorScheme>,
+    /// Also resize raster images to `size`, as a square PNG.
+    #[serde(default)]
+    pub resize_raster: bool,
+}
+
+impl RenderOptions {
+    fn wants_raster(&self) -> bool {
+        self.size.is_some() || self.color_scheme.is_some()
+    }
+}
+
+/// Only same-document fragments and embedded raster images are safe to keep.
+fn is_safe_reference(value: &str) -> bool {
+    let v = value.trim().to_lowercase();
+    v.starts_with('#')
+        || ["png", "jpeg", "jpg", "gif", "webp"]
+            .iter()
+            .any(|t| v.starts_with(&format!("data:image/{}", t)))
+}
+
+/// Replace `url()` references leaving the document with `none`.
+fn neutralize_urls(value: &str) -> String {
+    CSS_URL_RE
+        .replace_all(value, |caps: &regex::Captures| {
+            if is_safe_reference(&caps[1]) {
+                caps[0].to_string()
+            } else {
+                "none".to_string()
+            }
+        })
+        .to_string()
+}
+
+/// Drop `@import` rules and neutralize `url()` references leaving the document.
+fn sanitize_css(css: &str) -> String {
+    neutralize_urls(&IMPORT_RE.replace_all(css, ""))
+}
+
+fn sanitize_element(element: &BytesStart) -> Result<BytesStart<'static>, String> {
+    let name = String::from_utf8_lossy(element.name().as_ref()).to_string();
+    let mut clean = BytesStart::new(name);
+
+    for attr in element.attributes().with_checks(false) {
+        let attr = attr.map_err(|e| format!("Invalid SVG attribute: {}", e))?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+        let local_name = String::from_utf8_lossy(attr.key.local_name().as_ref()).to_lowercase();
+        let value = attr
+            .unescape_value()
+            .map_err(|e| format!("Invalid SVG attribute: {}", e))?
+            .to_string();
+
+        if local_name.starts_with("on") || value.to_lowercase().contains("javascript:") {
+            continue;
+        }
+        if URL_ATTRIBUTES.contains(&local_name.as_str()) && !is_safe_reference(&value) {
+            continue;
+        }
+
+        // Presentation attributes (`fill`, `filter`, `mask`, `marker-*`, ...)
+        // take `url()` values too.
+        let value = if local_name == "style" {
+            sanitize_css(&value)
+        } else {
+            neutralize_urls(&value)
+        };
+        clean.push_attribute((key.as_str(), value.as_str()));
+    }
+
+    Ok(clean)
+}
+
+fn local_name_lower(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).to_lowercase()
+}
+
+/// Strip scripts, event handlers, external references, DTDs and processing
+/// instructions from an SVG document.
+pub fn sanitize_svg(svg: &str) -> Result<String, String> {
+    let mut reader = Reader::from_str(svg);
+    let mut writer = Writer::new(Vec::new());
+    let mut skip_depth = 0usize;
+    let mut in_style = false;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid SVG: {}", e))?;
+
+        let out = match event {
+            Event::Eof => break,
+            // DTDs can declare entities (billion laughs, external entities) and
+            // PIs can pull in external stylesheets; neither is needed to render.
+            Event::DocType(_) | Event::PI(_) => None,
+            Event::Start(e) => {
+                if skip_depth > 0 || FORBIDDEN_ELEMENTS.contains(&local_name_lower(&e).as_str()) {
+                    skip_depth += 1;
+                    None
+                } else {
+                    in_style = local_name_lower(&e) == "style";
+                    Some(Event::Start(sanitize_element(&e)?))
+                }
+            }
+            Event::Empty(e) => {
+                if skip_depth > 0 || FORBIDDEN_ELEMENTS.contains(&local_name_lower(&e).as_str()) {
+                    None
+                } else {
+                    Some(Event::Empty(sanitize_element(&e)?))
+                }
+            }
+            Event::End(e) => {
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                    None
+                } else {
+                    in_style = false;
+                    Some(Event::End(e.into_owned()))
+                }
+            }
+            _ if skip_depth > 0 => None,
+            Event::Text(t) if in_style => {
+                let css = t
+                    .unescape()
+                    .map_err(|e| format!("Invalid SVG: {}", e))?
+                    .to_string();
+                Some(Event::Text(
+                    BytesText::new(&sanitize_css(&css)).into_owned(),
+                ))
+            }
+            Event::CData(c) if in_style => {
+                let css = String::from_utf8_lossy(&c).to_string();
+                Some(Event::CData(
+                    BytesCData::new(sanitize_css(&css)).into_owned(),
+                ))
+            }
+            other => Some(other.into_owned()),
+        };
+
+        if let Some(event) = out {
+            writer
+                .write_event(event)
+                .map_err(|e| format!("Failed to write SVG: {}", e))?;
+        }
+    }
+
+    String::from_utf8(writer.into_inner()).map_err(|e| format!("Failed to write SVG: {}", e))
+}
+
+/// Resolve `@media (prefers-color-scheme: …)` blocks for one scheme: blocks
+/// that select it and test nothing else are unwrapped in place, other
+/// color-scheme blocks removed. Other media queries are left alone.
+pub fn apply_color_scheme(css: &str, scheme: ColorScheme) -> String {
+    let wanted = match scheme {
+        ColorScheme::Light => "light",
+        ColorScheme::Dark => "dark",
+    };
+
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(caps) = MEDIA_RE.captures(rest) {
+        let whole = caps.get(0).unwrap();
+        let condition = caps[1].to_lowercase();
+
+        // Find the matching close brace for this @media block.
+        let body_start = whole.end();
+        let mut depth = 1;
+        let mut body_end = rest.len();
+        for (i, ch) in rest[body_start..].char_indices() {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        body_end = body_start + i;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let block_end = (body_end + 1).min(rest.len());
+
+        out.push_str(&rest[..whole.start()]);
+        if condition.contains("prefers-color-scheme") {
+            let query = condition.replace(char::is_whitespace, "");
+            // Anything combined with the scheme (print, widths) can't be
+            // evaluated for a rasterized icon, so it doesn't match.
+            if SCHEME_ONLY_RE.is_match(&query) && media_theme(&query) == Some(wanted) {
+                out.push_str(&rest[body_start..body_end]);
+            }
+        } else {
+            out.push_str(&rest[whole.start()..block_end]);
+        }
+        rest = &rest[block_end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// SVG parsing options that never touch the local filesystem: external
+/// image references are dropped, only `data:` URLs are resolved.
+pub(crate) fn svg_options() -> usvg::Options<'static> {
+    usvg::Options {
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_data: usvg::ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_, _| None),
+        },
+        ..usvg::Options::default()
+    }
+}
+
+/// Render an SVG into a transparent square canvas, scaled to fit and centered.
+pub(crate) fn render_svg(tree: &usvg::Tree, size: u32) -> Result<RgbaImage, String> {
+    let mut pixmap =
+        tiny_skia::Pixmap::new(size, size).ok_or_else(|| "Invalid render size".to_string())?;
+
+    let svg_size = tree.size();
+    let scale = (size as f32 / svg_size.width()).min(size as f32 / svg_size.height());
+    let dx = (size as f32 - svg_size.width() * scale) / 2.0;
+    let dy = (size as f32 - svg_size.height() * scale) / 2.0;
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(dx, dy);
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia stores premultiplied alpha; image expects straight alpha.
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(size, size, pixels).ok_or_else(|| "Failed to convert render".to_string())
+}
+
+/// Scale a raster image to fit a transparent square canvas, centered.
+pub(crate) fn fit_square(image: &RgbaImage, size: u32) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    let scale = (size as f32 / w as f32).min(size as f32 / h as f32);
+    let new_w = ((w as f32 * scale).round() as u32).clamp(1, size);
+    let new_h = ((h as f32 * scale).round() as u32).clamp(1, size);
+
+    let resized = imageops::resize(image, new_w, new_h, FilterType::Lanczos3);
+    let mut canvas = RgbaImage::new(size, size);
+    let x = ((size - new_w) / 2) as i64;
+    let y = ((size - new_h) / 2) as i64;
+    imageops::overlay(&mut canvas, &resized, x, y);
+    canvas
+}
+
+pub(crate) fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut buf = Cursor::new(Vec::new());
+    image
+        .write_to(&mut buf, ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(buf.into_inner())
+}
+
+/// Decode a raster image (the largest frame, for ICO) and resize it to a
+/// square PNG.
+pub fn resize_raster(bytes: &[u8], size: u32) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| format!("Failed to decode image: {}", e))?
+        .to_rgba8();
+    encode_png(&fit_square(&image, size.clamp(1, MAX_RASTER_SIZE)))
+}
+
+/// Sanitize an SVG and rasterize it to PNG bytes.
+///
+/// Without an explicit size the SVG's intrinsic size is used. Without a
+/// color scheme, `prefers-color-scheme` blocks resolve to light, matching
+/// what a browser does by default.
+pub fn rasterize_svg(svg: &[u8], options: &RenderOptions) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(svg).map_err(|_| "SVG is not valid UTF-8".to_string())?;
+    let clean = sanitize_svg(text)?;
+    let themed = apply_color_scheme(&clean, options.color_scheme.unwrap_or(ColorScheme::Light));
+
+    let tree = usvg::Tree::from_str(&themed, &svg_options())
+        .map_err(|e| format!("Failed to parse SVG: {}", e))?;
+
+    let intrinsic = tree.size().width().max(tree.size().height()).round() as u32;
+    let size = options
+        .size
+        .unwrap_or(if intrinsic > 0 {
+            intrinsic
+        } else {
+            DEFAULT_RASTER_SIZE
+        })
+        .clamp(1, MAX_RASTER_SIZE);
+
+    encode_png(&render_svg(&tree, size)?)
+}
+
+/// Make fetched image bytes safe to hand to a client: SVGs are sanitized, or
+/// rasterized to PNG when render options ask for it. Raster images are
+/// resized only with `resize_raster`, and otherwise pass through unchanged,
+/// as does anything that isn't an image. Returns the (possibly new) bytes
+/// and content type.
+pub fn prepare_image(
+    bytes: Vec<u8>,
+    content_type: String,
+    options: &RenderOptions,
+) -> Result<(Vec<u8>, String), String> {
+    // Magic bytes beat a mislabelled header; the header only decides when
+    // the bytes are inconclusive.
+    let sniffed = sniff_image_type(&bytes);
+    let is_svg = match sniffed {
+        Some(mime) => mime == "image/svg+xml",
+        None => content_type.contains("svg"),
+    };
+    if !is_svg {
+        return match options.size {
+            Some(size) if options.resize_raster && sniffed.is_some() => {
+                Ok((resize_raster(&bytes, size)?, "image/png".to_string()))
+            }
+            // Don't pass a raster image on under an SVG label.
+            _ => match sniffed {
+                Some(mime) if content_type.contains("svg") => Ok((bytes, mime.to_string())),
+                _ => Ok((bytes, content_type)),
+            },
+        };
+    }
+
+    if options.wants_raster() {
+        return Ok((rasterize_svg(&bytes, options)?, "image/png".to_string()));
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    let clean = sanitize_svg(&text)?;
+    Ok((clean.into_bytes(), "image/svg+xml".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_references_are_dropped_whatever_the_prefix() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:foo="http://www.w3.org/1999/xlink"><image foo:href="https://tracker.example/p.gif"/><image HREF="https://tracker.example/q.gif"/><use foo:href="#shape"/></svg>"##;
+        let clean = sanitize_svg(svg).unwrap();
+        assert!(!clean.contains("tracker"), "{}", clean);
+        assert!(clean.contains(r##"foo:href="#shape""##), "{}", clean);
+    }
+
+    #[test]
+    fn raster_bytes_win_over_an_svg_content_type() {
+        let png = encode_png(&RgbaImage::new(4, 4)).unwrap();
+        let (bytes, content_type) = prepare_image(
+            png.clone(),
+            "image/svg+xml".to_string(),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(bytes, png);
+        assert_eq!(content_type, "image/png");
+    }
+
+    #[test]
+    fn rasters_are_resized_only_on_request() {
+        let png = encode_png(&RgbaImage::new(64, 32)).unwrap();
+        let sized = RenderOptions {
+            size: Some(16),
+            ..RenderOptions::default()
+        };
+        let (bytes, _) = prepare_image(png.clone(), "image/png".to_string(), &sized).unwrap();
+        assert_eq!(bytes, png);
+
+        let resized = RenderOptions {
+            resize_raster: true,
+            ..sized
+        };
+        let (bytes, content_type) = prepare_image(png, "image/png".to_string(), &resized).unwrap();
+        assert_eq!(content_type, "image/png");
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 16));
+    }
+
+    #[test]
+    fn unsniffable_svg_falls_back_to_the_content_type() {
+        let svg = br#"<!-- logo --><g xmlns="http://www.w3.org/2000/svg"/>"#.to_vec();
+        let (_, content_type) =
+            prepare_image(svg, "image/svg+xml".to_string(), &RenderOptions::default()).unwrap();
+        assert_eq!(content_type, "image/svg+xml");
+    }
+
+    #[test]
+    fn presentation_attribute_urls_are_neutralized() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg"><rect fill="url(http://evil/x#g)" stroke="url( 'https://evil/s' )" filter="url(#blur)" marker-start="URL(//evil/m)" mask="red"/></svg>"##;
+        let clean = sanitize_svg(svg).unwrap();
+        assert!(!clean.contains("evil"), "{}", clean);
+        assert!(clean.contains(r#"fill="none""#), "{}", clean);
+        assert!(clean.contains(r##"filter="url(#blur)""##), "{}", clean);
+        assert!(clean.contains(r#"mask="red""#), "{}", clean);
+    }
+
+    #[test]
+    fn color_scheme_blocks_need_an_exact_match() {
+        let css = "a{x:0}\
+                   @media (prefers-color-scheme: dark){b{x:1}}\
+                   @media not (prefers-color-scheme: dark){c{x:2}}\
+                   @media (prefers-color-scheme: dark) and (print){d{x:3}}\
+                   @media screen and (prefers-color-scheme: light){e{x:4}}\
+                   @media (max-width: 600px){f{x:5}}";
+        assert_eq!(
+            apply_color_scheme(css, ColorScheme::Dark),
+            "a{x:0}b{x:1}@media (max-width: 600px){f{x:5}}"
+        );
+        assert_eq!(
+            apply_color_scheme(css, ColorScheme::Light),
+            "a{x:0}c{x:2}e{x:4}@media (max-width: 600px){f{x:5}}"
+        );
+    }
+}
@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use crate::error::ScanError;
+use crate::scanner::{ScanResult, Scanner};
+
+/// Output format for [`export_tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenFormat {
+    /// W3C Design Tokens Community Group JSON (Style Dictionary compatible).
+    W3c,
+    /// Tokens Studio for Figma JSON.
+    TokensStudio,
+    /// Tailwind `theme.extend` config snippet.
+    Tailwind,
+    /// CSS custom properties.
+    Css,
+}
+
+impl TokenFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            TokenFormat::W3c | TokenFormat::TokensStudio => "application/json",
+            TokenFormat::Tailwind => "text/javascript",
+            TokenFormat::Css => "text/css",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            TokenFormat::W3c => "tokens.json",
+            TokenFormat::TokensStudio => "tokens-studio.json",
+            TokenFormat::Tailwind => "tailwind.config.js",
+            TokenFormat::Css => "tokens.css",
+        }
+    }
+}
+
+/// Format-neutral token set derived from a scan.
+struct TokenSet {
+    colors: Vec<(String, String)>,
+    dark_colors: Vec<(String, String)>,
+    fonts: Vec<(String, String)>,
+}
+
+/// Lowercase a name to `[a-z0-9-]`, collapsing everything else to dashes.
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for ch in name.trim_start_matches("--").chars() {
+        if ch.is_ascii_alphanumeric() {
+            slug.push(ch.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Quote a value as a CSS string. Quotes and backslashes are escaped and
+/// control characters written as hex escapes, so no value can end the string
+/// (and with it the declaration) early.
+pub(crate) fn css_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(ch);
+            }
+            c if c.is_control() => quoted.push_str(&format!("\\{:x} ", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn push_unique(list: &mut Vec<(String, String)>, name: String, value: String) {
+    if !name.is_empty() && !list.iter().any(|(n, _)| *n == name) {
+        list.push((name, value));
+    }
+}
+
+fn collect_tokens(result: &ScanResult) -> TokenSet {
+    let mut colors = Vec::new();
+    let mut dark_colors = Vec::new();
+
+    // Named custom properties carry the site's own vocabulary.
+    for token in &result.palette.tokens {
+        if let Some(color) = &token.color {
+            let list = if token.theme == "dark" {
+                &mut dark_colors
+            } else {
+                &mut colors
+            };
+            push_unique(list, slugify(&token.name), color.clone());
+        }
+    }
+
+    // Palette colors not already covered by a named token.
+    let mut index = 0;
+    for color in &result.palette.colors {
+        if colors.iter().any(|(_, c)| *c == color.hex) {
+            continue;
+        }
+        index += 1;
+        push_unique(&mut colors, format!("palette-{}", index), color.hex.clone());
+    }
+
+    let mut fonts = Vec::new();
+    for font in &result.fonts {
+        push_unique(&mut fonts, slugify(&font.family), font.family.clone());
+    }
+
+    TokenSet {
+        colors,
+        dark_colors,
+        fonts,
+    }
+}
+
+fn w3c_json(set: &TokenSet) -> Value {
+    let color_group = |list: &[(String, String)]| -> Map<String, Value> {
+        list.iter()
+            .map(|(name, hex)| (name.clone(), json!({ "$type": "color", "$value": hex })))
+            .collect()
+    };
+
+    let mut color = color_group(&set.colors);
+    if !set.dark_colors.is_empty() {
+        color.insert(
+            "dark".to_string(),
+            Value::Object(color_group(&set.dark_colors)),
+        );
+    }
+    let font_family: Map<String, Value> = set
+        .fonts
+        .iter()
+        .map(|(name, family)| {
+            (
+                name.clone(),
+                json!({ "$type": "fontFamily", "$value": family }),
+            )
+        })
+        .collect();
+
+    json!({ "color": color, "fontFamily": font_family })
+}
+
+fn tokens_studio_json(set: &TokenSet) -> Value {
+    let color_set = |list: &[(String, String)]| -> Map<String, Value> {
+        list.iter()
+            .map(|(name, hex)| (name.clone(), json!({ "value": hex, "type": "color" })))
+            .collect()
+    };
+
+    let mut global = color_set(&set.colors);
+    for (name, family) in &set.fonts {
+        global.insert(
+            format!("font-{}", name),
+            json!({ "value": family, "type": "fontFamilies" }),
+        );
+    }
+
+    let mut order = vec!["global"];
+    let mut root = Map::new();
+    root.insert("global".to_string(), Value::Object(global));
+    if !set.dark_colors.is_empty() {
+        root.insert(
+            "dark".to_string(),
+            Value::Object(color_set(&set.dark_colors)),
+        );
+        order.push("dark");
+    }
+    root.insert("$metadata".to_string(), json!({ "tokenSetOrder": order }));
+    Value::Object(root)
+}
+
+fn tailwind_config(set: &TokenSet) -> Result<String, String> {
+    let colors: Map<String, Value> = set
+        .colors
+        .iter()
+        .map(|(name, hex)| (name.clone(), Value::String(hex.clone())))
+        .collect();
+    let font_family: Map<String, Value> = set
+        .fonts
+        .iter()
+        .map(|(name, family)| (name.clone(), json!([family])))
+        .collect();
+
+    let theme = json!({ "theme": { "extend": { "colors": colors, "fontFamily": font_family } } });
+    let body = serde_json::to_string_pretty(&theme)
+        .map_err(|e| format!("Failed to serialize tokens: {}", e))?;
+    Ok(format!(
+        "/** @type {{import('tailwindcss').Config}} */\nmodule.exports = {};\n",
+        body
+    ))
+}
+
+fn css_variables(set: &TokenSet) -> String {
+    let mut css = String::from(":root {\n");
+    for (name, hex) in &set.colors {
+        css.push_str(&format!("  --{}: {};\n", name, hex));
+    }
+    for (name, family) in &set.fonts {
+        css.push_str(&format!("  --font-{}: {};\n", name, css_string(family)));
+    }
+    css.push_str("}\n");
+
+    if !set.dark_colors.is_empty() {
+        css.push_str("\n@media (prefers-color-scheme: dark) {\n  :root {\n");
+        for (name, hex) in &set.dark_colors {
+            css.push_str(&format!("    --{}: {};\n", name, hex));
+        }
+        css.push_str("  }\n}\n");
+    }
+    css
+}
+
+/// Render a scan's colors and fonts as design tokens in the given format.
+pub fn export_tokens(result: &ScanResult, format: TokenFormat) -> Result<String, ScanError> {
+    let set = collect_tokens(result);
+    let pretty = |value: Value| {
+        serde_json::to_string_pretty(&value)
+            .map_err(|e| format!("Failed to serialize tokens: {}", e))
+    };
+
+    match format {
+        TokenFormat::W3c => pretty(w3c_json(&set)),
+        TokenFormat::TokensStudio => pretty(tokens_studio_json(&set)),
+        TokenFormat::Tailwind => tailwind_config(&set),
+        TokenFormat::Css => Ok(css_variables(&set)),
+    }
+    .map_err(ScanError::Internal)
+}
+
+/// Scan a website and export its design tokens.
+pub async fn export_tokens_from_url(
+    scanner: &Scanner,
+    url: &str,
+    format: TokenFormat,
+) -> Result<String, ScanError> {
+    let result = scanner.scan_website(url).await?;
+    export_tokens(&result, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{DesignToken, Palette, PaletteColor};
+    use crate::scanner::FontInfo;
+
+    fn token(name: &str, color: Option<&str>, theme: &str) -> DesignToken {
+        DesignToken {
+            name: name.to_string(),
+            value: color.unwrap_or("1rem").to_string(),
+            color: color.map(|c| c.to_string()),
+            theme: theme.to_string(),
+            selector: ":root".to_string(),
+        }
+    }
+
+    fn palette_color(hex: &str) -> PaletteColor {
+        PaletteColor {
+            hex: hex.to_string(),
+            count: 1,
+            sources: vec!["css".to_string()],
+            themes: vec!["default".to_string()],
+            merged: Vec::new(),
+        }
+    }
+
+    fn font(family: &str) -> FontInfo {
+        FontInfo {
+            family: family.to_string(),
+            variants: Vec::new(),
+            source: "css".to_string(),
+        }
+    }
+
+    fn scan_result() -> ScanResult {
+        ScanResult {
+            url: "https://example.com/".to_string(),
+            favicons: Vec::new(),
+            fonts: vec![font("Open Sans"), font("open  sans"), font(r#"Evil\";}"#)],
+            manifest: None,
+            tile: None,
+            theme_colors: Vec::new(),
+            images: Vec::new(),
+            logos: Vec::new(),
+            palette: Palette {
+                colors: vec![
+                    palette_color("#ff0000"),
+                    palette_color("#00ff00"),
+                    palette_color("#0000ff"),
+                ],
+                tokens: vec![
+                    token("--brand-primary", Some("#ff0000"), "default"),
+                    token("--Brand_Primary", Some("#00ff00"), "light"),
+                    token("--brand-primary", Some("#111111"), "dark"),
+                    token("--spacing", None, "default"),
+                ],
+            },
+            warnings: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn slugs_are_lowercase_and_dashed() {
+        assert_eq!(slugify("--Brand_Primary"), "brand-primary");
+        assert_eq!(slugify("  Open   Sans!! "), "open-sans");
+        assert_eq!(slugify("--"), "");
+    }
+
+    #[test]
+    fn colliding_slugs_keep_the_first_token() {
+        let set = collect_tokens(&scan_result());
+        // `--Brand_Primary` collides with `--brand-primary`, so its color
+        // falls through to the unnamed palette entries.
+        assert_eq!(
+            set.colors,
+            vec![
+                ("brand-primary".to_string(), "#ff0000".to_string()),
+                ("palette-1".to_string(), "#00ff00".to_string()),
+                ("palette-2".to_string(), "#0000ff".to_string()),
+            ]
+        );
+        assert_eq!(
+            set.dark_colors,
+            vec![("brand-primary".to_string(), "#111111".to_string())]
+        );
+        let fonts: Vec<&str> = set.fonts.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(fonts, vec!["open-sans", "evil"]);
+    }
+
+    #[test]
+    fn css_output() {
+        let css = export_tokens(&scan_result(), TokenFormat::Css).unwrap();
+        assert_eq!(
+            css,
+            r#":root {
+  --brand-primary: #ff0000;
+  --palette-1: #00ff00;
+  --palette-2: #0000ff;
+  --font-open-sans: "Open Sans";
+  --font-evil: "Evil\\\";}";
+}
+
+@media (prefers-color-scheme: dark) {
+  :root {
+    --brand-primary: #111111;
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn css_strings_cannot_be_closed_early() {
+        assert_eq!(css_string("Inter"), r#""Inter""#);
+        assert_eq!(css_string(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(css_string("line\nbreak"), r#""line\a break""#);
+    }
+
+    #[test]
+    fn w3c_output() {
+        let out = export_tokens(&scan_result(), TokenFormat::W3c).unwrap();
+        let value: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            value["color"]["brand-primary"],
+            json!({ "$type": "color", "$value": "#ff0000" })
+        );
+        assert_eq!(
+            value["color"]["dark"]["brand-primary"],
+            json!({ "$type": "color", "$value": "#111111" })
+        );
+        assert_eq!(
+            value["fontFamily"]["open-sans"],
+            json!({ "$type": "fontFamily", "$value": "Open Sans" })
+        );
+    }
+
+    #[test]
+    fn tokens_studio_output() {
+        let out = export_tokens(&scan_result(), TokenFormat::TokensStudio).unwrap();
+        let value: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            value["global"]["palette-2"],
+            json!({ "value": "#0000ff", "type": "color" })
+        );
+        assert_eq!(
+            value["global"]["font-open-sans"],
+            json!({ "value": "Open Sans", "type": "fontFamilies" })
+        );
+        assert_eq!(
+            value["dark"]["brand-primary"],
+            json!({ "value": "#111111", "type": "color" })
+        );
+        assert_eq!(
+            value["$metadata"],
+            json!({ "tokenSetOrder": ["global", "dark"] })
+        );
+    }
+
+    #[test]
+    fn tailwind_output() {
+        let out = export_tokens(&scan_result(), TokenFormat::Tailwind).unwrap();
+        let body = out
+            .strip_prefix("/** @type {import('tailwindcss').Config} */\nmodule.exports = ")
+            .and_then(|rest| rest.strip_suffix(";\n"))
+            .unwrap();
+        let value: Value = serde_json::from_str(body).unwrap();
+        let extend = &value["theme"]["extend"];
+        assert_eq!(extend["colors"]["brand-primary"], "#ff0000");
+        assert_eq!(extend["fontFamily"]["evil"], json!([r#"Evil\";}"#]));
+        // Tailwind has no dark token set; dark colors stay out of the config.
+        assert_eq!(extend["colors"].as_object().unwrap().len(), 3);
+    }
+}
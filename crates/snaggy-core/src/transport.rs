@@ -0,0 +1,99 @@
+use reqwest::{Certificate, ClientBuilder, NoProxy, Proxy};
+use url::Url;
+
+use crate::config::Config;
+use crate::error::ScanError;
+
+/// Apply the configured proxies and TLS trust settings to a client.
+pub(crate) fn configure(
+    mut builder: ClientBuilder,
+    cfg: &Config,
+) -> Result<ClientBuilder, ScanError> {
+    for proxy in proxies(cfg)? {
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &cfg.ca_certificates {
+        let pem = std::fs::read(path)
+            .map_err(|e| ScanError::Config(format!("ca_certificates: {}: {}", path, e)))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| ScanError::Config(format!("ca_certificates: {}: {}", path, e)))?;
+        if certs.is_empty() {
+            return Err(ScanError::Config(format!(
+                "ca_certificates: {}: no PEM certificates found",
+                path
+            )));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    Ok(builder
+        .tls_built_in_native_certs(cfg.tls_native_roots)
+        .danger_accept_invalid_certs(cfg.tls_insecure))
+}
+
+/// Scheme-specific proxies first, so they win over the catch-all one.
+fn proxies(cfg: &Config) -> Result<Vec<Proxy>, ScanError> {
+    let no_proxy = NoProxy::from_string(&cfg.no_proxy.join(","));
+    let configured = [
+        (&cfg.http_proxy, "http"),
+        (&cfg.https_proxy, "https"),
+        (&cfg.proxy, "all"),
+    ];
+
+    let mut proxies = Vec::new();
+    for (url, scheme) in configured {
+        let Some(url) = url else { continue };
+        let proxy = match scheme {
+            "http" => Proxy::http(url),
+            "https" => Proxy::https(url),
+            _ => Proxy::all(url),
+        };
+        let mut proxy = proxy.map_err(|e| ScanError::Config(format!("proxy {}: {}", url, e)))?;
+        if let (Some(username), Some(password)) = (&cfg.proxy_username, &cfg.proxy_password) {
+            proxy = proxy.basic_auth(username, password);
+        }
+        proxies.push(proxy.no_proxy(no_proxy.clone()));
+    }
+    Ok(proxies)
+}
+
+/// Whether `host` is one of the configured proxies. Proxies are trusted
+/// infrastructure, so they are exempt from the private-address check.
+pub(crate) fn is_proxy_host(cfg: &Config, host: &str) -> bool {
+    [&cfg.proxy, &cfg.http_proxy, &cfg.https_proxy]
+        .into_iter()
+        .flatten()
+        .filter_map(|url| Url::parse(url).ok())
+        .any(|url| url.host_str() == Some(host))
+}
+
+/// Whether a request to `url` may go through a proxy, configured or taken
+/// from the standard environment variables. `no_proxy` exclusions are
+/// ignored, so this errs towards yes.
+pub(crate) fn may_use_proxy(cfg: &Config, url: &Url) -> bool {
+    let configured = [&cfg.proxy, &cfg.http_proxy, &cfg.https_proxy];
+    if configured.iter().any(|p| p.is_some()) {
+        return cfg.proxy.is_some()
+            || match url.scheme() {
+                "http" => cfg.http_proxy.is_some(),
+                "https" => cfg.https_proxy.is_some(),
+                _ => false,
+            };
+    }
+
+    // reqwest only reads the environment when no proxy was configured.
+    let set = |names: [&str; 2]| {
+        names
+            .iter()
+            .any(|n| std::env::var(n).is_ok_and(|v| !v.trim().is_empty()))
+    };
+    set(["ALL_PROXY", "all_proxy"])
+        || match url.scheme() {
+            "http" => set(["HTTP_PROXY", "http_proxy"]),
+            "https" => set(["HTTPS_PROXY", "https_proxy"]),
+            _ => false,
+        }
+}
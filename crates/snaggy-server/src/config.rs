@@ -0,0 +1,118 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+
+use axum::http::HeaderValue;
+use snaggy_core::config::{Config, ConfigError, ConfigLayer, CONFIG_PATH_ENV};
+use tower_http::cors::{Any, CorsLayer};
+
+const USAGE: &str = "Usage: snaggy-server [--config <path>]";
+
+/// Listen address, CORS policy and credential sharing, from the `[server]`
+/// section of the config file and `SNAGGY_PORT` / `SNAGGY_BIND` /
+/// `SNAGGY_CORS_ORIGINS` / `SNAGGY_SHARE_CREDENTIALS`.
+pub struct ServerConfig {
+    pub addr: SocketAddr,
+    /// `*` allows any origin.
+    pub cors_origins: Vec<String>,
+    /// Whether the configured `[[credentials]]` apply to API callers' scans.
+    pub share_credentials: bool,
+}
+
+impl ServerConfig {
+    fn from_layers(layers: &[ConfigLayer]) -> Result<Self, ConfigError> {
+        let mut port = 3001;
+        let mut bind = "0.0.0.0".to_string();
+        let mut cors_origins = vec!["*".to_string()];
+        let mut share_credentials = false;
+        for layer in layers {
+            let server = &layer.server;
+            if let Some(p) = server.port {
+                port = p;
+            }
+            if let Some(b) = &server.bind {
+                bind = b.clone();
+            }
+            if let Some(origins) = &server.cors_origins {
+                cors_origins = origins.clone();
+            }
+            if let Some(share) = server.share_credentials {
+                share_credentials = share;
+            }
+        }
+
+        let ip: IpAddr = bind.parse().map_err(|_| ConfigError {
+            key: "server.bind".to_string(),
+            message: format!("\"{}\" is not an IP address", bind),
+        })?;
+        if let Some(origin) = cors_origins
+            .iter()
+            .find(|o| *o != "*" && HeaderValue::from_str(o).is_err())
+        {
+            return Err(ConfigError {
+                key: "server.cors_origins".to_string(),
+                message: format!("\"{}\" is not a valid origin", origin),
+            });
+        }
+
+        Ok(Self {
+            addr: SocketAddr::new(ip, port),
+            cors_origins,
+            share_credentials,
+        })
+    }
+
+    pub fn cors_layer(&self) -> CorsLayer {
+        if self.cors_origins.iter().any(|o| o == "*") {
+            return CorsLayer::permissive();
+        }
+        let origins: Vec<HeaderValue> = self
+            .cors_origins
+            .iter()
+            .filter_map(|o| HeaderValue::from_str(o).ok())
+            .collect();
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(Any)
+            .allow_headers(Any)
+    }
+}
+
+/// `--config <path>` from the command line, else `SNAGGY_CONFIG`.
+fn config_path() -> Result<Option<PathBuf>, String> {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            path = Some(args.next().ok_or_else(|| USAGE.to_string())?);
+        } else if let Some(value) = arg.strip_prefix("--config=") {
+            path = Some(value.to_string());
+        } else {
+            return Err(format!("Unknown argument \"{}\"\n{}", arg, USAGE));
+        }
+    }
+    Ok(path
+        .or_else(|| std::env::var(CONFIG_PATH_ENV).ok())
+        .map(PathBuf::from))
+}
+
+/// Load the scanner and server settings: defaults < config file < environment.
+pub fn load() -> Result<(Config, ServerConfig), String> {
+    let mut layers = Vec::new();
+    if let Some(path) = config_path()? {
+        println!("Loading config from {}", path.display());
+        layers.push(ConfigLayer::from_file(&path).map_err(|e| e.to_string())?);
+    }
+    layers.push(ConfigLayer::from_env().map_err(|e| e.to_string())?);
+
+    let mut config = Config::from_layers(&layers).map_err(|e| e.to_string())?;
+    let server = ServerConfig::from_layers(&layers).map_err(|e| e.to_string())?;
+    // Anyone who can reach the API could otherwise scan with the operator's logins.
+    if !server.share_credentials && !config.credentials.is_empty() {
+        println!(
+            "Ignoring {} [[credentials]] entries; set server.share_credentials to send them on every scan",
+            config.credentials.len()
+        );
+        config.credentials.clear();
+    }
+    Ok((config, server))
+}
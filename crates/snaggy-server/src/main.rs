@@ -1,21 +1,111 @@
+mod config;
+
 use axum::{
-    extract::Query,
+    body::Body,
+    extract::{Query, State},
     http::{header, StatusCode},
-    response::{IntoResponse, Json},
-    routing::get,
+    response::{IntoResponse, Json, Redirect, Response},
+    routing::{get, post},
     Router,
 };
 use serde::Deserialize;
-use tower_http::cors::CorsLayer;
+use snaggy_core::bulk::BulkRequest;
+use snaggy_core::error::ScanError;
+use snaggy_core::generator::FaviconSetOptions;
+use snaggy_core::options::ScanOptions;
+use snaggy_core::scanner::Scanner;
+use snaggy_core::svg::{ColorScheme, RenderOptions};
+use snaggy_core::tokens::TokenFormat;
 
 #[derive(Deserialize)]
 struct UrlParam {
     url: String,
 }
 
-async fn api_scan(Query(params): Query<UrlParam>) -> impl IntoResponse {
-    eprintln!("[scan] url={}", params.url);
-    match snaggy_core::scanner::scan_website(&params.url).await {
+#[derive(Deserialize)]
+struct ScanRequest {
+    url: String,
+    #[serde(default)]
+    options: ScanOptions,
+}
+
+#[derive(Deserialize)]
+struct ImageParams {
+    url: String,
+    size: Option<u32>,
+    color_scheme: Option<ColorScheme>,
+}
+
+impl ImageParams {
+    fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            size: self.size,
+            color_scheme: self.color_scheme,
+            resize_raster: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct IconParams {
+    url: String,
+    size: Option<u32>,
+    #[serde(default)]
+    redirect: bool,
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    url: String,
+    format: TokenFormat,
+}
+
+#[derive(Deserialize)]
+struct KitParams {
+    url: String,
+    /// Comma-separated family names; all families when omitted.
+    families: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FaviconSetParams {
+    url: String,
+    #[serde(flatten)]
+    options: FaviconSetOptions,
+}
+
+/// HTTP status for a failed request. Upstream failures are gateway errors;
+/// the body carries the machine-readable `code` (and upstream `status`).
+fn status_for(error: &ScanError) -> StatusCode {
+    match error {
+        ScanError::InvalidUrl(_) | ScanError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        ScanError::Blocked(_) => StatusCode::FORBIDDEN,
+        ScanError::NotFound(_) => StatusCode::NOT_FOUND,
+        ScanError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        ScanError::Dns(_)
+        | ScanError::Tls(_)
+        | ScanError::Connection(_)
+        | ScanError::UpstreamStatus { .. }
+        | ScanError::TooLarge { .. } => StatusCode::BAD_GATEWAY,
+        ScanError::Parse(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        ScanError::Io(_) | ScanError::Config(_) | ScanError::Internal(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+fn error_response(tag: &str, error: ScanError) -> Response {
+    eprintln!("[{}] ERROR: {}", tag, error);
+    (
+        status_for(&error),
+        Json(serde_json::json!({ "error": error })),
+    )
+        .into_response()
+}
+
+async fn scan(scanner: &Scanner, url: &str) -> Response {
+    eprintln!("[scan] url={}", url);
+    match scanner.scan_website(url).await {
         Ok(result) => {
             eprintln!(
                 "[scan] OK: {} favicons, {} fonts",
@@ -24,94 +114,268 @@ async fn api_scan(Query(params): Query<UrlParam>) -> impl IntoResponse {
             );
             (StatusCode::OK, Json(serde_json::json!(result))).into_response()
         }
-        Err(e) => {
-            eprintln!("[scan] ERROR: {}", e);
-            (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
-        }
+        Err(e) => error_response("scan", e),
     }
 }
 
-async fn api_proxy_image(Query(params): Query<UrlParam>) -> impl IntoResponse {
-    match snaggy_core::scanner::proxy_image(&params.url).await {
-        Ok(data_url) => {
-            (StatusCode::OK, Json(serde_json::json!({ "data": data_url }))).into_response()
-        }
-        Err(e) => {
-            eprintln!("[proxy-image] ERROR: {}", e);
-            (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
-        }
+async fn api_scan(
+    State(scanner): State<Scanner>,
+    Query(params): Query<UrlParam>,
+) -> impl IntoResponse {
+    scan(&scanner, &params.url).await
+}
+
+/// Scan with per-request overrides (`{ "url", "options": ScanOptions }`).
+async fn api_scan_with_options(
+    State(scanner): State<Scanner>,
+    Json(request): Json<ScanRequest>,
+) -> impl IntoResponse {
+    match scanner.with_options(&request.options) {
+        Ok(scanner) => scan(&scanner, &request.url).await,
+        Err(e) => error_response("scan", e),
+    }
+}
+
+async fn api_proxy_image(
+    State(scanner): State<Scanner>,
+    Query(params): Query<ImageParams>,
+) -> impl IntoResponse {
+    let options = params.render_options();
+    match scanner.proxy_image(&params.url, &options).await {
+        Ok(data_url) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "data": data_url })),
+        )
+            .into_response(),
+        Err(e) => error_response("proxy-image", e),
     }
 }
 
-async fn api_download(Query(params): Query<UrlParam>) -> impl IntoResponse {
-    match snaggy_core::scanner::download_asset_bytes(&params.url).await {
-        Ok((bytes, content_type)) => {
-            let filename = params
+async fn api_download(
+    State(scanner): State<Scanner>,
+    Query(params): Query<ImageParams>,
+) -> impl IntoResponse {
+    let options = params.render_options();
+    match scanner
+        .open_asset_download(&params.url, &options, None)
+        .await
+    {
+        Ok(download) => {
+            let mut filename = params
                 .url
                 .split('/')
-                .last()
+                .next_back()
                 .and_then(|s| s.split('?').next())
-                .unwrap_or("download");
+                .unwrap_or("download")
+                .to_string();
+
+            // Rasterized SVGs come back as PNG.
+            if download.content_type == "image/png" && filename.to_lowercase().ends_with(".svg") {
+                filename.truncate(filename.len() - 4);
+                filename.push_str(".png");
+            }
 
-            (
+            let mut response = (
                 StatusCode::OK,
                 [
-                    (header::CONTENT_TYPE, content_type),
+                    (header::CONTENT_TYPE, download.content_type),
                     (
                         header::CONTENT_DISPOSITION,
                         format!("attachment; filename=\"{}\"", filename),
                     ),
                 ],
-                bytes,
-            )
-                .into_response()
-        }
-        Err(e) => {
-            eprintln!("[download] ERROR: {}", e);
-            (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": e })),
+                Body::from_stream(download.body),
             )
-                .into_response()
+                .into_response();
+            if let Some(len) = download.content_length {
+                response
+                    .headers_mut()
+                    .insert(header::CONTENT_LENGTH, len.into());
+            }
+            response
         }
+        Err(e) => error_response("download", e),
+    }
+}
+
+async fn api_icon(
+    State(scanner): State<Scanner>,
+    Query(params): Query<IconParams>,
+) -> impl IntoResponse {
+    let best = match snaggy_core::ranking::best_icon(&scanner, &params.url, params.size).await {
+        Ok(best) => best,
+        Err(e) => return error_response("icon", e),
+    };
+
+    if params.redirect && params.size.is_none() {
+        return Redirect::temporary(&best.icon.url).into_response();
+    }
+
+    let options = RenderOptions {
+        size: params.size,
+        color_scheme: None,
+        resize_raster: true,
+    };
+    match scanner
+        .open_asset_download(&best.icon.url, &options, None)
+        .await
+    {
+        Ok(download) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, download.content_type),
+                (header::CONTENT_LOCATION, best.icon.url),
+            ],
+            Body::from_stream(download.body),
+        )
+            .into_response(),
+        Err(e) => error_response("icon", e),
+    }
+}
+
+async fn api_favicon_set(
+    State(scanner): State<Scanner>,
+    Query(params): Query<FaviconSetParams>,
+) -> impl IntoResponse {
+    let result = snaggy_core::generator::generate_favicon_set_from_url(
+        &scanner,
+        &params.url,
+        &params.options,
+    )
+    .await
+    .and_then(|set| set.to_zip());
+
+    match result {
+        Ok(zip) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"favicons.zip\"".to_string(),
+                ),
+            ],
+            zip,
+        )
+            .into_response(),
+        Err(e) => error_response("favicon-set", e),
+    }
+}
+
+async fn api_export(
+    State(scanner): State<Scanner>,
+    Query(params): Query<ExportParams>,
+) -> impl IntoResponse {
+    match snaggy_core::tokens::export_tokens_from_url(&scanner, &params.url, params.format).await {
+        Ok(body) => (
+            StatusCode::OK,
+            [
+                (
+                    header::CONTENT_TYPE,
+                    format!("{}; charset=utf-8", params.format.content_type()),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", params.format.file_name()),
+                ),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(e) => error_response("export", e),
+    }
+}
+
+async fn api_kit(
+    State(scanner): State<Scanner>,
+    Query(params): Query<KitParams>,
+) -> impl IntoResponse {
+    let families: Vec<String> = params
+        .families
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|f| f.trim().to_string())
+        .filter(|f| !f.is_empty())
+        .collect();
+
+    let result = snaggy_core::kit::build_font_kit_from_url(&scanner, &params.url, &families)
+        .await
+        .and_then(|kit| kit.to_zip());
+
+    match result {
+        Ok(zip) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"fonts.zip\"".to_string(),
+                ),
+            ],
+            zip,
+        )
+            .into_response(),
+        Err(e) => error_response("kit", e),
+    }
+}
+
+async fn api_bulk(
+    State(scanner): State<Scanner>,
+    Json(request): Json<BulkRequest>,
+) -> impl IntoResponse {
+    match snaggy_core::bulk::bulk_download(&scanner, &request).await {
+        Ok(chunks) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"assets.zip\"".to_string(),
+                ),
+            ],
+            Body::from_stream(chunks),
+        )
+            .into_response(),
+        Err(e) => error_response("bulk", e),
     }
 }
 
 #[tokio::main]
 async fn main() {
-    let port: u16 = std::env::var("SNAGGY_PORT")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(3001);
+    let (config, server) = config::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let scanner = Scanner::new(config).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
 
     let api = Router::new()
-        .route("/api/scan", get(api_scan))
+        .route("/api/scan", get(api_scan).post(api_scan_with_options))
         .route("/api/proxy-image", get(api_proxy_image))
-        .route("/api/download", get(api_download));
+        .route("/api/download", get(api_download))
+        .route("/api/favicon-set", get(api_favicon_set))
+        .route("/api/icon", get(api_icon))
+        .route("/api/export", get(api_export))
+        .route("/api/kit", get(api_kit))
+        .route("/api/bulk", post(api_bulk))
+        .with_state(scanner);
 
     // In production, serve the built frontend from ./dist
     let app = if std::path::Path::new("./dist").exists() {
         api.fallback_service(
-            tower_http::services::ServeDir::new("./dist")
-                .append_index_html_on_directories(true),
+            tower_http::services::ServeDir::new("./dist").append_index_html_on_directories(true),
         )
     } else {
         api
     };
 
-    let app = app.layer(CorsLayer::permissive());
+    let app = app.layer(server.cors_layer());
 
-    let addr = format!("0.0.0.0:{}", port);
-    println!("Snaggy server listening on http://{}", addr);
+    println!("Snaggy server listening on http://{}", server.addr);
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    let listener = tokio::net::TcpListener::bind(server.addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
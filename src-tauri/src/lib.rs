@@ -1,5 +1,24 @@
 mod scanner;
 
+use std::path::PathBuf;
+
+use snaggy_core::config::{Config, CONFIG_PATH_ENV};
+use snaggy_core::scanner::Scanner;
+use tauri::{App, Manager};
+
+/// `SNAGGY_CONFIG`, else `config.toml` / `config.yaml` / `config.yml` in the
+/// app's config directory, if present.
+fn config_path(app: &App) -> Option<PathBuf> {
+    if let Ok(path) = std::env::var(CONFIG_PATH_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let dir = app.path().app_config_dir().ok()?;
+    ["config.toml", "config.yaml", "config.yml"]
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -13,12 +32,19 @@ pub fn run() {
                         .build(),
                 )?;
             }
+            let config = Config::load(config_path(app).as_deref())?;
+            app.manage(Scanner::new(config)?);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             scanner::scan_website,
             scanner::download_asset,
             scanner::proxy_image,
+            scanner::generate_favicon_set,
+            scanner::best_icon,
+            scanner::export_tokens,
+            scanner::save_font_kit,
+            scanner::download_bulk,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,19 +1,114 @@
-// Thin Tauri command wrappers around snaggy-core.
-use snaggy_core::scanner;
+// Thin Tauri command wrappers around snaggy-core. Errors reach the frontend
+// as a serialized `ScanError` (`{ code, message, ... }`). All commands share
+// the `Scanner` managed by the app.
+use std::sync::Arc;
 
-use scanner::ScanResult;
+use snaggy_core::bulk::{self, BulkRequest};
+use snaggy_core::download::{DownloadProgress, ProgressCallback};
+use snaggy_core::error::ScanError;
+use snaggy_core::generator::{self, FaviconSetOptions};
+use snaggy_core::kit;
+use snaggy_core::options::ScanOptions;
+use snaggy_core::ranking::{self, RankedIcon};
+use snaggy_core::scanner::{ScanResult, Scanner};
+use snaggy_core::svg::{ColorScheme, RenderOptions};
+use snaggy_core::tokens::{self, TokenFormat};
+
+use tauri::{AppHandle, Emitter, State};
+
+#[tauri::command]
+pub async fn scan_website(
+    scanner: State<'_, Scanner>,
+    url: String,
+    options: Option<ScanOptions>,
+) -> Result<ScanResult, ScanError> {
+    match options {
+        Some(options) => scanner.with_options(&options)?.scan_website(&url).await,
+        None => scanner.scan_website(&url).await,
+    }
+}
+
+/// Emits `download-progress` events with a `DownloadProgress` payload.
+#[tauri::command]
+pub async fn download_asset(
+    app: AppHandle,
+    scanner: State<'_, Scanner>,
+    url: String,
+    save_path: String,
+    size: Option<u32>,
+    color_scheme: Option<ColorScheme>,
+) -> Result<(), ScanError> {
+    let options = RenderOptions {
+        size,
+        color_scheme,
+        resize_raster: false,
+    };
+    let progress: ProgressCallback = Arc::new(move |p: &DownloadProgress| {
+        let _ = app.emit("download-progress", p);
+    });
+    scanner
+        .download_asset(&url, &save_path, &options, Some(progress))
+        .await
+}
+
+#[tauri::command]
+pub async fn proxy_image(
+    scanner: State<'_, Scanner>,
+    url: String,
+    size: Option<u32>,
+    color_scheme: Option<ColorScheme>,
+) -> Result<String, ScanError> {
+    let options = RenderOptions {
+        size,
+        color_scheme,
+        resize_raster: false,
+    };
+    scanner.proxy_image(&url, &options).await
+}
+
+#[tauri::command]
+pub async fn generate_favicon_set(
+    scanner: State<'_, Scanner>,
+    url: String,
+    options: FaviconSetOptions,
+    save_path: String,
+) -> Result<(), ScanError> {
+    generator::save_favicon_set(&scanner, &url, &options, &save_path).await
+}
+
+#[tauri::command]
+pub async fn best_icon(
+    scanner: State<'_, Scanner>,
+    url: String,
+    size: Option<u32>,
+) -> Result<RankedIcon, ScanError> {
+    ranking::best_icon(&scanner, &url, size).await
+}
 
 #[tauri::command]
-pub async fn scan_website(url: String) -> Result<ScanResult, String> {
-    scanner::scan_website(&url).await
+pub async fn export_tokens(
+    scanner: State<'_, Scanner>,
+    url: String,
+    format: TokenFormat,
+) -> Result<String, ScanError> {
+    tokens::export_tokens_from_url(&scanner, &url, format).await
 }
 
 #[tauri::command]
-pub async fn download_asset(url: String, save_path: String) -> Result<(), String> {
-    scanner::download_asset(&url, &save_path).await
+pub async fn save_font_kit(
+    scanner: State<'_, Scanner>,
+    url: String,
+    families: Vec<String>,
+    save_dir: String,
+) -> Result<(), ScanError> {
+    kit::save_font_kit(&scanner, &url, &families, &save_dir).await
 }
 
 #[tauri::command]
-pub async fn proxy_image(url: String) -> Result<String, String> {
-    scanner::proxy_image(&url).await
+pub async fn download_bulk(
+    scanner: State<'_, Scanner>,
+    request: BulkRequest,
+    save_path: String,
+) -> Result<(), ScanError> {
+    bulk::save_bulk_download(&scanner, &request, &save_path).await
 }
@@ -65,6 +65,11 @@ function FaviconCard({ favicon }: { favicon: FaviconInfo }) {
             {favicon.sizes}
           </span>
         )}
+        {favicon.media?.includes("dark") && (
+          <span className="rounded-md bg-muted px-1.5 py-0.5 text-[10px] font-medium text-muted-foreground">
+            dark
+          </span>
+        )}
       </div>
     </div>
   );
@@ -1,4 +1,4 @@
-import type { ScanResult } from "@/lib/types";
+import type { ScanErrorInfo, ScanResult } from "@/lib/types";
 import { isTauri } from "@/lib/tauri";
 
 const API_BASE = "/api";
@@ -7,12 +7,43 @@ const API_BASE = "/api";
  * Unified API layer: uses Tauri invoke in desktop mode, HTTP fetch in web mode.
  */
 
+/** A backend failure with its machine-readable `code`. */
+export class ApiError extends Error {
+  code: string;
+  status?: number;
+  limit?: number;
+
+  constructor(info: ScanErrorInfo) {
+    super(info.message);
+    this.name = "ApiError";
+    this.code = info.code;
+    this.status = info.status;
+    this.limit = info.limit;
+  }
+}
+
+function toError(value: unknown, fallback: string): Error {
+  if (value && typeof value === "object" && "code" in value) {
+    return new ApiError(value as ScanErrorInfo);
+  }
+  return new Error(typeof value === "string" && value ? value : fallback);
+}
+
+async function responseError(res: Response): Promise<Error> {
+  const body = await res.json().catch(() => ({}));
+  return toError(body.error, `Request failed with status ${res.status}`);
+}
+
 async function tauriInvoke<T>(
   command: string,
   args: Record<string, unknown>,
 ): Promise<T> {
   const { invoke } = await import("@tauri-apps/api/core");
-  return invoke<T>(command, args);
+  try {
+    return await invoke<T>(command, args);
+  } catch (e) {
+    throw toError(e, `${command} failed`);
+  }
 }
 
 async function httpGet<T>(
@@ -25,37 +56,175 @@ async function httpGet<T>(
   }
   const res = await fetch(url.toString());
   if (!res.ok) {
-    const body = await res.json().catch(() => ({}));
-    throw new Error(body.error || `Request failed with status ${res.status}`);
+    throw await responseError(res);
   }
   return res.json() as Promise<T>;
 }
 
-export async function scanWebsite(url: string): Promise<ScanResult> {
+export type Extractor =
+  | "manifest"
+  | "browserconfig"
+  | "well-known-icons"
+  | "icon-details"
+  | "stylesheets"
+  | "images"
+  | "logos";
+
+/** Headers, cookies and credentials sent only to matching hosts. */
+export interface SiteCredentials {
+  /** Host patterns; `*.example.com` matches subdomains. */
+  hosts: string[];
+  headers?: Record<string, string>;
+  basic_auth?: { username: string; password?: string };
+  bearer_token?: string;
+  cookies?: Record<string, string>;
+}
+
+/**
+ * Per-scan overrides. Timeouts (seconds) and size limits (bytes) can only
+ * lower the configured values.
+ */
+export interface ScanOptions {
+  user_agent?: string;
+  timeout_request?: number;
+  timeout_image?: number;
+  max_stylesheets?: number;
+  max_imports?: number;
+  max_html_size?: number;
+  max_css_size?: number;
+  max_image_size?: number;
+  max_font_size?: number;
+  max_download_size?: number;
+  max_retries?: number;
+  /** Extractors to run; all of them when omitted. */
+  extractors?: Extractor[];
+  /** Added to the configured credentials. */
+  credentials?: SiteCredentials[];
+}
+
+export async function scanWebsite(
+  url: string,
+  options?: ScanOptions,
+): Promise<ScanResult> {
   if (isTauri) {
-    return tauriInvoke<ScanResult>("scan_website", { url });
+    return tauriInvoke<ScanResult>("scan_website", { url, options });
+  }
+  if (!options) {
+    return httpGet<ScanResult>("/scan", { url });
   }
-  return httpGet<ScanResult>("/scan", { url });
+  const res = await fetch(`${API_BASE}/scan`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ url, options }),
+  });
+  if (!res.ok) {
+    throw await responseError(res);
+  }
+  return res.json() as Promise<ScanResult>;
 }
 
-export async function proxyImage(url: string): Promise<string> {
+export interface RenderOptions {
+  /** Rasterize SVGs to a square PNG of this many pixels. */
+  size?: number;
+  /** Resolve `prefers-color-scheme` styling when rasterizing SVGs. */
+  color_scheme?: "light" | "dark";
+}
+
+function renderParams(options: RenderOptions): Record<string, string> {
+  const params: Record<string, string> = {};
+  if (options.size) params.size = String(options.size);
+  if (options.color_scheme) params.color_scheme = options.color_scheme;
+  return params;
+}
+
+export async function proxyImage(
+  url: string,
+  options: RenderOptions = {},
+): Promise<string> {
   if (isTauri) {
-    return tauriInvoke<string>("proxy_image", { url });
+    return tauriInvoke<string>("proxy_image", {
+      url,
+      size: options.size,
+      colorScheme: options.color_scheme,
+    });
   }
-  const res = await httpGet<{ data: string }>("/proxy-image", { url });
+  const res = await httpGet<{ data: string }>("/proxy-image", {
+    url,
+    ...renderParams(options),
+  });
   return res.data;
 }
 
+export interface DownloadProgress {
+  url: string;
+  downloaded: number;
+  /** From `Content-Length`, when the server sent one. */
+  total: number | null;
+}
+
 export async function downloadAsset(
   url: string,
   savePath?: string,
+  options: RenderOptions = {},
+  onProgress?: (progress: DownloadProgress) => void,
 ): Promise<void> {
   if (isTauri && savePath) {
-    return tauriInvoke<void>("download_asset", { url, savePath });
+    const { listen } = await import("@tauri-apps/api/event");
+    const unlisten = onProgress
+      ? await listen<DownloadProgress>("download-progress", (event) => {
+          if (event.payload.url === url) onProgress(event.payload);
+        })
+      : undefined;
+    try {
+      return await tauriInvoke<void>("download_asset", {
+        url,
+        savePath,
+        size: options.size,
+        colorScheme: options.color_scheme,
+      });
+    } finally {
+      unlisten?.();
+    }
   }
   // Web mode: trigger browser download
   const downloadUrl = new URL(`${API_BASE}/download`, window.location.origin);
   downloadUrl.searchParams.set("url", url);
+  for (const [k, v] of Object.entries(renderParams(options))) {
+    downloadUrl.searchParams.set(k, v);
+  }
+  const a = document.createElement("a");
+  a.href = downloadUrl.toString();
+  a.download = "";
+  document.body.appendChild(a);
+  a.click();
+  document.body.removeChild(a);
+}
+
+export interface FaviconSetOptions {
+  name?: string;
+  short_name?: string;
+  theme_color?: string;
+  background_color?: string;
+}
+
+export async function generateFaviconSet(
+  url: string,
+  options: FaviconSetOptions = {},
+  savePath?: string,
+): Promise<void> {
+  if (isTauri && savePath) {
+    return tauriInvoke<void>("generate_favicon_set", {
+      url,
+      options,
+      savePath,
+    });
+  }
+  // Web mode: trigger browser download of the generated ZIP
+  const downloadUrl = new URL(`${API_BASE}/favicon-set`, window.location.origin);
+  downloadUrl.searchParams.set("url", url);
+  for (const [k, v] of Object.entries(options)) {
+    if (v) downloadUrl.searchParams.set(k, v);
+  }
   const a = document.createElement("a");
   a.href = downloadUrl.toString();
   a.download = "";
@@ -63,3 +232,93 @@ export async function downloadAsset(
   a.click();
   document.body.removeChild(a);
 }
+
+export type TokenFormat = "w3c" | "tokens-studio" | "tailwind" | "css";
+
+/** Export the site's colors and fonts as design tokens. */
+export async function exportTokens(
+  url: string,
+  format: TokenFormat,
+): Promise<string> {
+  if (isTauri) {
+    return tauriInvoke<string>("export_tokens", { url, format });
+  }
+  const exportUrl = new URL(`${API_BASE}/export`, window.location.origin);
+  exportUrl.searchParams.set("url", url);
+  exportUrl.searchParams.set("format", format);
+  const res = await fetch(exportUrl.toString());
+  if (!res.ok) {
+    throw await responseError(res);
+  }
+  return res.text();
+}
+
+/**
+ * Download a self-hosting kit (font files plus `fonts.css`) for the given
+ * families, or all families when none are given.
+ */
+export async function downloadFontKit(
+  url: string,
+  families: string[] = [],
+  saveDir?: string,
+): Promise<void> {
+  if (isTauri && saveDir) {
+    return tauriInvoke<void>("save_font_kit", { url, families, saveDir });
+  }
+  // Web mode: trigger browser download of the kit ZIP
+  const downloadUrl = new URL(`${API_BASE}/kit`, window.location.origin);
+  downloadUrl.searchParams.set("url", url);
+  if (families.length > 0) {
+    downloadUrl.searchParams.set("families", families.join(","));
+  }
+  const a = document.createElement("a");
+  a.href = downloadUrl.toString();
+  a.download = "";
+  document.body.appendChild(a);
+  a.click();
+  document.body.removeChild(a);
+}
+
+export type AssetKind = "icon" | "font" | "image" | "logo";
+
+export interface BulkAsset {
+  url: string;
+  kind?: AssetKind;
+  /** Font family, used for the `fonts/<family>/` folder. */
+  family?: string;
+}
+
+export interface BulkRequest {
+  assets?: BulkAsset[];
+  /** Website to scan; its assets are added, filtered by `include`. */
+  scan_url?: string;
+  /** Kinds of scanned assets to include; all kinds when empty. */
+  include?: AssetKind[];
+}
+
+/** Download many assets as one ZIP (with a `manifest.json` of results). */
+export async function downloadBulk(
+  request: BulkRequest,
+  savePath?: string,
+): Promise<void> {
+  if (isTauri && savePath) {
+    return tauriInvoke<void>("download_bulk", { request, savePath });
+  }
+  // Web mode: POST the request, then hand the ZIP to the browser
+  const res = await fetch(`${API_BASE}/bulk`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(request),
+  });
+  if (!res.ok) {
+    throw await responseError(res);
+  }
+  const blobUrl = URL.createObjectURL(await res.blob());
+  const a = document.createElement("a");
+  a.href = blobUrl;
+  a.download = "assets.zip";
+  document.body.appendChild(a);
+  a.click();
+  document.body.removeChild(a);
+  URL.revokeObjectURL(blobUrl);
+}
@@ -2,6 +2,101 @@ export interface ScanResult {
   url: string;
   favicons: FaviconInfo[];
   fonts: FontInfo[];
+  manifest: ManifestInfo | null;
+  tile: TileInfo | null;
+  theme_colors: ThemeColor[];
+  images: SocialImage[];
+  logos: LogoCandidate[];
+  palette: Palette;
+  warnings: ScanWarning[];
+  diagnostics: Diagnostic[];
+}
+
+export interface ScanWarning {
+  /** "truncated" (partially used) or "too-large" (skipped). */
+  kind: string;
+  resource: string;
+  url: string;
+  limit: number;
+}
+
+/** One request made during a scan, successful or not. */
+export interface Diagnostic {
+  /** "html", "css", "manifest", "browserconfig", "icon-probe" or "icon". */
+  resource: string;
+  url: string;
+  status: number | null;
+  /** Extra attempts after timeouts, dropped connections or busy responses. */
+  retries: number;
+  duration_ms: number;
+  /** Body bytes received (decompressed), else `Content-Length`. */
+  size: number | null;
+  content_type: string | null;
+  bytes_parsed: number | null;
+  /** `@font-face` rules found (stylesheets only). */
+  faces_found: number | null;
+  /** Same codes as `ScanErrorInfo.code`. */
+  error_code: string | null;
+  error: string | null;
+}
+
+/** Error returned by the backend (HTTP `error` field or Tauri rejection). */
+export interface ScanErrorInfo {
+  /**
+   * "invalid-url", "invalid-request", "not-found", "blocked", "dns",
+   * "timeout", "tls", "connection", "upstream-status", "too-large",
+   * "parse", "io", "config" or "internal".
+   */
+  code: string;
+  message: string;
+  /** Upstream HTTP status, for "upstream-status". */
+  status?: number;
+  /** Size limit in bytes, for "too-large". */
+  limit?: number;
+}
+
+export interface Palette {
+  colors: PaletteColor[];
+  tokens: DesignToken[];
+}
+
+export interface PaletteColor {
+  hex: string;
+  count: number;
+  sources: string[];
+  themes: string[];
+  merged: string[];
+}
+
+export interface DesignToken {
+  name: string;
+  value: string;
+  color: string | null;
+  theme: string;
+  selector: string;
+}
+
+export interface LogoCandidate {
+  url: string | null;
+  svg: string | null;
+  kind: string;
+  alt: string | null;
+  score: number;
+  evidence: string[];
+}
+
+export interface SocialImage {
+  url: string;
+  kind: string;
+  width: number | null;
+  height: number | null;
+  alt: string | null;
+  mime_type: string | null;
+}
+
+export interface ThemeColor {
+  color: string;
+  media: string | null;
 }
 
 export interface FaviconInfo {
@@ -9,6 +104,41 @@ export interface FaviconInfo {
   rel: string;
   sizes: string | null;
   mime_type: string | null;
+  media: string | null;
+  color: string | null;
+  purpose: string | null;
+  source: string;
+  details: IconDetails | null;
+}
+
+export interface IconDetails {
+  mime_type: string;
+  width: number | null;
+  height: number | null;
+  bit_depth: number | null;
+  file_size: number;
+  frames: IconFrame[];
+  size_mismatch: boolean;
+}
+
+export interface IconFrame {
+  width: number;
+  height: number;
+  bit_depth: number | null;
+  format: string;
+}
+
+export interface ManifestInfo {
+  url: string;
+  name: string | null;
+  short_name: string | null;
+  theme_color: string | null;
+  background_color: string | null;
+}
+
+export interface TileInfo {
+  config_url: string | null;
+  tile_color: string | null;
 }
 
 export interface FontInfo {
@@ -22,4 +152,5 @@ export interface FontVariant {
   weight: string;
   url: string;
   format: string;
+  descriptors: Record<string, string>;
 }